@@ -1,9 +1,12 @@
+use crate::config::{Config, Edition, Features};
 use crate::entry::{Entry, Expected};
-use crate::result::BatchResult;
+use crate::result::{BatchResult, BatchRunResult};
 use crate::runner::Runner;
 use std::cell::RefCell;
 use std::path::Path;
 use std::thread;
+use std::time::Duration;
+use termcolor::WriteColor;
 
 #[derive(Debug, Default)]
 pub struct Batch {
@@ -19,22 +22,50 @@ impl Batch {
         }
     }
 
-    pub fn run_match<P: AsRef<Path>>(&self, path: P) {
-        self.runner
-            .borrow_mut()
-            .add_entry(Entry::new(path, Expected::RunMatch));
+    pub fn run_match<P: AsRef<Path>>(&self, path: P) -> EntryBuilder<'_> {
+        self.add_entry(Entry::new(path, Expected::RunMatch))
     }
 
-    pub fn compile_fail<P: AsRef<Path>>(&self, path: P) {
-        self.runner
-            .borrow_mut()
-            .add_entry(Entry::new(path, Expected::CompileFail));
+    pub fn run_pass<P: AsRef<Path>>(&self, path: P) -> EntryBuilder<'_> {
+        self.add_entry(Entry::new(path, Expected::RunPass))
+    }
+
+    pub fn run_fail<P: AsRef<Path>>(&self, path: P) -> EntryBuilder<'_> {
+        self.add_entry(Entry::new(path, Expected::RunFail))
+    }
+
+    pub fn compile_pass<P: AsRef<Path>>(&self, path: P) -> EntryBuilder<'_> {
+        self.add_entry(Entry::new(path, Expected::CompilePass))
+    }
+
+    pub fn compile_fail<P: AsRef<Path>>(&self, path: P) -> EntryBuilder<'_> {
+        self.add_entry(Entry::new(path, Expected::CompileFail))
+    }
+
+    fn add_entry(&self, entry: Entry) -> EntryBuilder<'_> {
+        let index = self.runner.borrow_mut().add_entry(entry);
+        EntryBuilder {
+            runner: &self.runner,
+            index,
+        }
     }
 
     pub fn run(mut self) -> BatchResult {
         self.has_run = true;
         self.runner.borrow_mut().run()
     }
+
+    /// Run the batch with the provided configuration.
+    ///
+    /// Since the entries can be run on several threads, the writer must be `Send`,
+    /// and the closure passed to `WriterBuilder::new` must be `Send + Sync`.
+    pub fn run_with_config<W: WriteColor + Send>(
+        mut self,
+        cfg: Config<W>,
+    ) -> BatchResult<BatchRunResult<W>> {
+        self.has_run = true;
+        self.runner.borrow_mut().run_with_config(cfg)
+    }
 }
 
 #[doc(hidden)]
@@ -49,3 +80,116 @@ impl Drop for Batch {
         }
     }
 }
+
+/// Handle to the entry queued in the batch, which can be used to adjust its settings.
+///
+/// If the pattern is a glob, the settings apply to every matching file. The settings
+/// related to the entry process (timeout, input, arguments, environment and exit code)
+/// only have effect on the entries which are run.
+pub struct EntryBuilder<'a> {
+    runner: &'a RefCell<Runner>,
+    index: usize,
+}
+
+impl EntryBuilder<'_> {
+    /// Kill the entry process if it runs longer than `timeout`, overriding the batch default.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.update(|entry| entry.set_timeout(timeout))
+    }
+
+    /// Feed the given bytes to the entry process as its standard input.
+    ///
+    /// Without this, the input is taken from the adjacent _*.stdin_ file, if it exists.
+    pub fn stdin<B: Into<Vec<u8>>>(self, stdin: B) -> Self {
+        let stdin = stdin.into();
+        self.update(|entry| entry.set_stdin(stdin))
+    }
+
+    /// Pass the argument to the entry process.
+    pub fn arg<S: Into<String>>(self, arg: S) -> Self {
+        let arg = arg.into();
+        self.update(|entry| entry.add_arg(arg))
+    }
+
+    /// Pass the arguments to the entry process.
+    pub fn args<I, S>(self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        args.into_iter().fold(self, EntryBuilder::arg)
+    }
+
+    /// Set the environment variable for the entry process.
+    ///
+    /// The rest of the environment is inherited from the current process.
+    pub fn env<K: Into<String>, V: Into<String>>(self, key: K, value: V) -> Self {
+        let (key, value) = (key.into(), value.into());
+        self.update(|entry| entry.set_env(key, value))
+    }
+
+    /// Require the entry process to exit with the given code.
+    ///
+    /// This is checked before the output is compared with the snapshot.
+    pub fn exit_code(self, code: i32) -> Self {
+        self.update(|entry| entry.set_exit_code(code))
+    }
+
+    /// Require the `run_fail` entry to panic with the message containing the pattern.
+    pub fn panic_message<S: Into<String>>(self, pattern: S) -> Self {
+        let pattern = pattern.into();
+        self.update(|entry| entry.set_panic_message(pattern))
+    }
+
+    /// Build the entry with the given Rust edition instead of the one used by the crate.
+    pub fn edition(self, edition: Edition) -> Self {
+        self.update(|entry| entry.set_edition(edition))
+    }
+
+    /// Build the entry against the crate compiled with the given features enabled,
+    /// in addition to the default ones.
+    pub fn features<I, S>(self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.feature_set(Features::new(features))
+    }
+
+    /// Build the entry against the crate compiled with the given set of features,
+    /// e.g. with `Features::all()`, replacing the ones set for the batch.
+    pub fn feature_set(self, features: Features) -> Self {
+        self.update(|entry| entry.set_features(features))
+    }
+
+    /// Look for the expected output files (_*.stderr_ or _*.snapshot_) in the given directory,
+    /// instead of the one containing the entry.
+    pub fn snapshot_dir<P: AsRef<Path>>(self, dir: P) -> Self {
+        let dir = dir.as_ref().to_owned();
+        self.update(|entry| entry.set_snapshot_dir(dir))
+    }
+
+    /// Link the `compile_pass` entry, instead of only type-checking it.
+    pub fn link(self, link: bool) -> Self {
+        self.update(|entry| entry.set_link(link))
+    }
+
+    /// Compare the compiler warnings for the `compile_pass` entry with the _*.stderr_ file,
+    /// in the same way as the errors are compared for the `compile_fail` entries.
+    pub fn snapshot_warnings(self, snapshot_warnings: bool) -> Self {
+        self.update(|entry| entry.set_snapshot_warnings(snapshot_warnings))
+    }
+
+    /// Don't run the entry, reporting it as ignored with the given reason.
+    ///
+    /// The same can be done with the `// batch_run: ignore = "reason"` comment in the entry itself.
+    pub fn ignore<S: Into<String>>(self, reason: S) -> Self {
+        let reason = reason.into();
+        self.update(|entry| entry.set_ignore(reason))
+    }
+
+    fn update(self, f: impl FnOnce(&mut Entry)) -> Self {
+        f(self.runner.borrow_mut().entry_mut(self.index));
+        self
+    }
+}
@@ -1,14 +1,19 @@
 use crate::cargo_rustc;
+use crate::config::{BuildConfig, Edition, Probe};
+use crate::probe;
 use crate::result::BatchResult;
+use crate::rustc_args;
 use lazy_static::lazy_static;
 use rand::random;
 use std::{
+    collections::HashMap,
     env::var_os,
     ffi::OsString,
     fs::{create_dir, remove_dir, remove_file, write},
     ops::Not,
     path::{Path, PathBuf},
     process::Command,
+    sync::{Arc, Mutex},
 };
 
 lazy_static! {
@@ -19,7 +24,7 @@ lazy_static! {
     ]
     .iter()
     .collect();
-    pub static ref BUILDER: BinaryBuilder = BinaryBuilder::new().unwrap();
+    static ref BUILDERS: Mutex<HashMap<BuildConfig, Arc<BinaryBuilder>>> = Mutex::default();
 }
 
 fn new() -> BatchResult<(String, bool)> {
@@ -38,31 +43,9 @@ fn new() -> BatchResult<(String, bool)> {
     Ok((name, bin_created))
 }
 
-fn into_builder(name: &str) -> BatchResult<BinaryBuilder> {
-    let cmd = cargo_rustc::capture_build_command(name)?;
-
-    let args = cmd
-        .split_ascii_whitespace()
-        .scan("", |prev, cur| {
-            let out = if prev == &"-L" {
-                vec!["-L", cur]
-            } else if prev == &"--cfg" {
-                vec!["--cfg", cur]
-            } else if prev == &"--extern" {
-                vec!["--extern", cur]
-            } else if cur.starts_with("--edition") {
-                vec![cur]
-            } else {
-                vec![]
-            };
-            *prev = cur;
-            Some(out)
-        })
-        .flatten()
-        .map(String::from)
-        .collect();
-
-    Ok(BinaryBuilder { args })
+fn into_args(name: &str, build: &BuildConfig) -> BatchResult<Vec<String>> {
+    let cmd = cargo_rustc::capture_build_command(name, build)?;
+    rustc_args::forwarded(&cmd, build.forwarded_flags())
 }
 fn drop(name: &str, bin_created: bool) {
     remove_file(BIN_DIR.join(name).with_extension("rs")).unwrap_or_else(|_| {
@@ -83,18 +66,61 @@ fn drop(name: &str, bin_created: bool) {
     }
 }
 
+#[derive(Debug)]
 pub struct BinaryBuilder {
     args: Vec<String>,
+    target: Option<String>,
+    runner: Vec<String>,
+    linker: Option<String>,
 }
 
 impl BinaryBuilder {
-    pub fn new() -> BatchResult<Self> {
-        let (name, bin_created) = new()?;
-        let builder = into_builder(&name);
-        drop(&name, bin_created);
-        builder
+    pub fn new(build: &BuildConfig) -> BatchResult<Self> {
+        let args = match build.probe() {
+            Probe::Metadata => probe::builder_args(build)?,
+            Probe::SourceBin => {
+                let (name, bin_created) = new()?;
+                let args = into_args(&name, build);
+                drop(&name, bin_created);
+                args?
+            }
+        };
+        Ok(Self {
+            args,
+            target: build.target().map(String::from),
+            runner: build.target_runner(),
+            linker: build.target_linker(),
+        })
+    }
+    /// Builder for the given configuration, shared by all batches in the process.
+    ///
+    /// The lock is held while probing, so that the same configuration is never probed twice.
+    pub fn cached(build: &BuildConfig) -> BatchResult<Arc<Self>> {
+        let mut builders = BUILDERS.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(builder) = builders.get(build) {
+            return Ok(Arc::clone(builder));
+        }
+        let builder = Arc::new(Self::new(build)?);
+        builders.insert(build.clone(), Arc::clone(&builder));
+        Ok(builder)
+    }
+    pub fn args_to_command(&self, cmd: &mut Command, main: &Path, edition: Option<Edition>) {
+        match edition {
+            Some(edition) => cmd
+                .args(self.args.iter().filter(|arg| !arg.starts_with("--edition")))
+                .arg(format!("--edition={}", edition.as_str())),
+            None => cmd.args(&self.args),
+        };
+        if let Some(target) = &self.target {
+            cmd.arg("--target").arg(target);
+        }
+        if let Some(linker) = &self.linker {
+            cmd.arg("-C").arg(format!("linker={}", linker));
+        }
+        cmd.arg(main);
     }
-    pub fn args_to_command(&self, cmd: &mut Command, main: &Path) {
-        cmd.args(&self.args).arg(main);
+    /// Command the built entries are run through, if any.
+    pub fn runner(&self) -> &[String] {
+        &self.runner
     }
 }
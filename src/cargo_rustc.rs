@@ -1,67 +1,169 @@
 use crate::result::{error::BatchError, BatchResult};
-use crate::result::{error::EntryError, EntryResult};
-use lazy_static::lazy_static;
+use crate::result::{
+    error::{EntryError, EntryFailed},
+    EntryResult,
+};
 use std::{
+    collections::BTreeMap,
     env::{consts::EXE_EXTENSION, var_os},
+    fs::{create_dir_all, remove_file},
+    io::{self, Read, Write},
     path::{Path, PathBuf},
-    process::{Command, Output},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::binary::BinaryBuilder;
+use crate::config::{BuildConfig, Edition};
 use crate::rustflags;
 
-lazy_static! {
-    static ref TARGET_BIN: PathBuf = {
-        let mut tmp: PathBuf = [".", "target", "batch"].iter().collect();
+include!(concat!(env!("OUT_DIR"), "/info.rs"));
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Output executable of the single entry build.
+///
+/// Every entry run gets its own unique path, so that the entries built at the same time
+/// (either by the parallel workers or by several batches in one process) don't overwrite
+/// each other's binaries. The file is removed when the artifact is dropped.
+pub struct Artifact {
+    path: PathBuf,
+}
+
+impl Artifact {
+    pub fn new() -> EntryResult<Self> {
+        let mut path = manifest_dir().join("target").join("batch");
         // TODO configurable?
-        std::fs::create_dir_all(&tmp).expect("Unable to create batch executable in target directory; check your access rights");
-        tmp.push(&format!("{:x}", rand::random::<u64>()));
-        tmp.with_extension(EXE_EXTENSION)
-    };
+        create_dir_all(&path)?;
+        path.push(format!("{:x}", rand::random::<u64>()));
+        path.set_extension(EXE_EXTENSION);
+        Ok(Self { path })
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 }
 
-include!(concat!(env!("OUT_DIR"), "/info.rs"));
+impl Drop for Artifact {
+    fn drop(&mut self) {
+        // the entry could have failed before anything was written, so the missing file is fine
+        let _ = remove_file(&self.path);
+        if cfg!(windows) {
+            let _ = remove_file(self.path.with_extension("pdb"));
+        }
+    }
+}
+
+/// Directory of the crate being tested.
+pub fn manifest_dir() -> PathBuf {
+    PathBuf::from(var_os("CARGO_MANIFEST_DIR").expect("Couldn't get manifest dir"))
+}
+
+/// Resolve the path of an entry or a snapshot against the directory of the crate.
+///
+/// The working directory of the process is never changed, since several batches
+/// can be run at the same time (e.g. by the parallel tests).
+pub fn in_manifest_dir(path: &Path) -> PathBuf {
+    manifest_dir().join(path)
+}
 
 fn raw_cargo() -> Command {
     Command::new(option_env!("CARGO").unwrap_or("cargo"))
 }
 
-fn rustc() -> Command {
+fn rustc(artifact: &Artifact) -> Command {
     let mut cmd = Command::new(info::rustc());
-    cmd.current_dir(var_os("CARGO_MANIFEST_DIR").unwrap());
-    cmd.args(&[
-        "-o",
-        TARGET_BIN.to_str().expect("Non-UTF-8 symbols in path"),
-    ]);
+    cmd.current_dir(manifest_dir());
+    cmd.arg("-o").arg(artifact.path());
     cmd
 }
 
-pub fn capture_build_command(bin_name: &str) -> BatchResult<String> {
+/// Arguments for cargo which select the profile, target and features of the build.
+fn build_config_args(cmd: &mut Command, build: &BuildConfig) {
+    match build.profile() {
+        Some(profile) => {
+            cmd.arg("--profile").arg(profile);
+        }
+        None if info::opt_level() == "release" => {
+            cmd.arg("--release");
+        }
+        None => {}
+    };
+    if let Some(target) = build.target() {
+        cmd.arg("--target").arg(target);
+    }
+    cmd.args(build.features().cargo_args());
+}
+
+pub fn capture_build_command(bin_name: &str, build: &BuildConfig) -> BatchResult<String> {
     let mut cmd = raw_cargo();
-    cmd.current_dir(var_os("CARGO_MANIFEST_DIR").unwrap());
+    cmd.current_dir(manifest_dir());
     rustflags::set_env(&mut cmd);
     cmd.arg("build");
-    if info::opt_level() == "release" {
-        cmd.arg("--release");
-    };
+    build_config_args(&mut cmd, build);
     cmd.arg("--bin")
         .arg(bin_name)
         .arg("--verbose")
         .output()
         .map_err(BatchError::Cargo)
-        .map_err(Into::into)
         // .map(|out| { println!("Cargo output: \"{}\"", String::from_utf8(out.clone().stderr).unwrap()); out })
         .map(extract_build_command)
         .map(trim_build_command)
 }
 
+fn manifest_path() -> PathBuf {
+    manifest_dir().join("Cargo.toml")
+}
+
+/// Output of `cargo metadata` for the current crate and its dependencies.
+pub fn metadata(build: &BuildConfig) -> BatchResult<Vec<u8>> {
+    let mut cmd = raw_cargo();
+    cmd.arg("metadata")
+        .arg("--format-version=1")
+        .arg("--manifest-path")
+        .arg(manifest_path());
+    if let Some(target) = build.target() {
+        cmd.arg("--filter-platform").arg(target);
+    }
+    cmd.args(build.features().cargo_args());
+    checked_output(cmd).map(|out| out.stdout)
+}
+
+/// JSON messages of `cargo build` for the current crate, one per line.
+///
+/// `target` selects the crate targets to be built, e.g. `--lib`.
+pub fn build_messages(build: &BuildConfig, target: &str) -> BatchResult<Vec<u8>> {
+    let mut cmd = raw_cargo();
+    rustflags::set_env(&mut cmd);
+    cmd.arg("build")
+        .arg("--manifest-path")
+        .arg(manifest_path())
+        .arg(target)
+        .arg("--message-format=json-render-diagnostics");
+    build_config_args(&mut cmd, build);
+    checked_output(cmd).map(|out| out.stdout)
+}
+
+fn checked_output(mut cmd: Command) -> BatchResult<Output> {
+    cmd.current_dir(manifest_dir());
+    let out = cmd.output().map_err(BatchError::Cargo)?;
+    if out.status.success() {
+        Ok(out)
+    } else {
+        Err(BatchError::Probe(
+            String::from_utf8_lossy(&out.stderr).trim().to_owned(),
+        ))
+    }
+}
+
 fn extract_build_command(out: Output) -> String {
     String::from_utf8(out.stderr)
         .expect("Cargo produced non-UTF-8 output")
         .lines()
         // .inspect(|line| println!("Cargo output: {}", line))
-        .filter(|line| line.trim_start().starts_with("Running `"))
-        .last()
+        .rfind(|line| line.trim_start().starts_with("Running `"))
         .expect("No running command in cargo output")
         .to_owned()
 }
@@ -73,20 +175,171 @@ fn trim_build_command(line: String) -> String {
         .to_owned()
 }
 
-pub fn build_entry(builder: &BinaryBuilder, main: &Path, run: bool) -> EntryResult<Output> {
-    let mut cmd = rustc();
-    builder.args_to_command(&mut cmd, main);
-    cmd.arg(if run {
-        "--emit=link"
-    } else {
-        "--emit=dep-info"
-    });
+/// Kind of output requested from the compiler.
+#[derive(Copy, Clone, Debug)]
+pub enum Emit {
+    /// Full build, producing the executable.
+    Link,
+    /// Type-checking only.
+    Metadata,
+    /// Used when we only want to see the compiler diagnostics.
+    DepInfo,
+}
+
+impl Emit {
+    fn as_arg(self) -> &'static str {
+        match self {
+            Emit::Link => "--emit=link",
+            Emit::Metadata => "--emit=metadata",
+            Emit::DepInfo => "--emit=dep-info",
+        }
+    }
+}
+
+pub fn build_entry(
+    builder: &BinaryBuilder,
+    main: &Path,
+    edition: Option<Edition>,
+    flags: &[String],
+    emit: Emit,
+    artifact: &Artifact,
+) -> EntryResult<Output> {
+    let mut cmd = rustc(artifact);
+    builder.args_to_command(&mut cmd, main, edition);
+    cmd.args(flags);
+    cmd.arg(emit.as_arg());
     cmd.output().map_err(EntryError::Rustc).map_err(Into::into)
 }
 
-pub fn run_entry() -> EntryResult<Output> {
-    Command::new(&*TARGET_BIN)
-        .output()
-        .map_err(EntryError::RunFailed)
-        .map_err(Into::into)
+/// Command running the artifact, possibly through the runner, e.g. an emulator.
+fn entry_command(artifact: &Artifact, runner: &[String]) -> Command {
+    let mut cmd = match runner.split_first() {
+        Some((program, runner_args)) => {
+            let mut cmd = Command::new(program);
+            cmd.args(runner_args).arg(artifact.path());
+            cmd
+        }
+        None => Command::new(artifact.path()),
+    };
+    cmd.current_dir(manifest_dir());
+    cmd
+}
+
+pub fn run_entry(
+    artifact: &Artifact,
+    runner: &[String],
+    args: &[String],
+    env: &BTreeMap<String, String>,
+    stdin: Option<&[u8]>,
+    timeout: Option<Duration>,
+) -> EntryResult<Output> {
+    let mut cmd = entry_command(artifact, runner);
+    // the processes started by the entry are put into the same group, so that they are killed too
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd
+        .args(args)
+        .envs(env)
+        .stdin(match stdin {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(EntryError::RunFailed)?;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // the entry is not obliged to read all of its input, so the writer is never waited for
+    write_in_background(child.stdin.take(), stdin.map(<[u8]>::to_vec));
+    // the pipes must be drained while we're waiting, or the child could block on writing
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    let timed_out = |child: &mut Child| -> EntryResult<Output> {
+        kill(child)?;
+        Err(EntryFailed::Timeout(timeout.unwrap_or_default()))
+    };
+    let status = match deadline {
+        Some(deadline) => match wait_until(&mut child, deadline)? {
+            Some(status) => status,
+            None => return timed_out(&mut child),
+        },
+        None => child.wait()?,
+    };
+    // the output pipes can be held open by the processes the entry has started,
+    // so the readers are waited for only until the same deadline
+    match (receive(&stdout, deadline), receive(&stderr, deadline)) {
+        (Some(stdout), Some(stderr)) => Ok(Output {
+            status,
+            stdout: stdout?,
+            stderr: stderr?,
+        }),
+        _ => timed_out(&mut child),
+    }
+}
+
+fn wait_until(child: &mut Child, deadline: Instant) -> io::Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Kill the entry together with every process in its group.
+fn kill(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    // SAFETY: `kill` has no memory safety requirements; the group is alive while it has members,
+    // so its ID can't be reused by an unrelated process
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    // the entry itself could have exited already, in which case there's nothing to kill
+    let _ = child.kill();
+    child.wait()?;
+    Ok(())
+}
+
+/// Wait for the output of the reader thread, giving up at the deadline.
+fn receive<T>(receiver: &Receiver<T>, deadline: Option<Instant>) -> Option<T> {
+    let res = match deadline {
+        Some(deadline) => {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(res) => res,
+                Err(RecvTimeoutError::Timeout) => return None,
+                Err(RecvTimeoutError::Disconnected) => panic!("Output reading thread panicked"),
+            }
+        }
+        None => receiver.recv().expect("Output reading thread panicked"),
+    };
+    Some(res)
+}
+
+fn write_in_background<W: Write + Send + 'static>(target: Option<W>, input: Option<Vec<u8>>) {
+    thread::spawn(move || {
+        // the pipe is closed when the target is dropped, so the entry sees the end of input
+        if let (Some(mut target), Some(input)) = (target, input) {
+            let _ = target.write_all(&input);
+        }
+    });
+}
+
+fn read_in_background<R: Read + Send + 'static>(
+    source: Option<R>,
+) -> Receiver<io::Result<Vec<u8>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let res = match source {
+            Some(mut source) => source.read_to_end(&mut buf).map(|_| buf),
+            None => Ok(buf),
+        };
+        // the receiver is gone if the entry has timed out
+        let _ = sender.send(res);
+    });
+    receiver
 }
@@ -1,19 +1,16 @@
+use crate::events::Events;
+use crate::reporter::{ColoredReporter, Reporter};
 use crate::result::{error::BatchError, error::ConfigError, BatchResult};
-use std::{env, rc::Rc};
+use std::{env, io::Write, sync::Arc, time::Duration};
 use termcolor::{Buffer, ColorChoice, StandardStream, WriteColor};
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
 pub enum Update {
+    #[default]
     Wip,
     Overwrite,
 }
 
-impl Default for Update {
-    fn default() -> Self {
-        Update::Wip
-    }
-}
-
 impl Update {
     fn env() -> BatchResult<Self> {
         let var = match env::var_os("BATCH_RUN") {
@@ -29,19 +26,298 @@ impl Update {
     }
 }
 
-pub struct WriterBuilder<W: WriteColor>(Rc<dyn Fn() -> W>);
+/// The way the mismatch between expected and actual output is shown.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum MismatchView {
+    /// Show only the changed lines with some context around them, highlighting the changed words.
+    #[default]
+    Diff,
+    /// Show both the expected and the actual outputs in full.
+    Full,
+}
+
+/// Rust edition used to build the entries.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Edition {
+    E2015,
+    E2018,
+    E2021,
+    E2024,
+}
+
+impl Edition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+}
+
+/// Cargo features of the crate, which are enabled when building the entries.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Default)]
+pub struct Features {
+    features: Vec<String>,
+    all_features: bool,
+    no_default_features: bool,
+}
+
+impl Features {
+    /// Enable the given features in addition to the default ones.
+    pub fn new<I, S>(features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            features: features.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+    /// Enable all features of the crate.
+    pub fn all() -> Self {
+        Self {
+            all_features: true,
+            ..Self::default()
+        }
+    }
+    /// Disable the default features, so that only the explicitly listed ones are enabled.
+    pub fn without_default(self) -> Self {
+        Self {
+            no_default_features: true,
+            ..self
+        }
+    }
+    /// Arguments for `cargo build` which select these features.
+    pub(crate) fn cargo_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if !self.features.is_empty() {
+            args.push("--features".to_owned());
+            args.push(self.features.join(","));
+        }
+        if self.all_features {
+            args.push("--all-features".to_owned());
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_owned());
+        }
+        args
+    }
+}
+
+/// The way the crate build is inspected to find the arguments for compiling the entries.
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Default)]
+pub enum Probe {
+    /// Read `cargo metadata` and the JSON messages of `cargo build`.
+    /// Nothing is written into the source tree.
+    #[default]
+    Metadata,
+    /// Add a temporary binary to `src/bin` and capture the `rustc` command cargo uses for it.
+    SourceBin,
+}
+
+/// Flags of the `rustc` command captured with `Probe::SourceBin`,
+/// which are passed on when building the entries.
+///
+/// Flags are named as in `rustc --help`, e.g. `"-C"` or `"--check-cfg"`. The flags describing
+/// the probe binary itself, such as `--crate-name` or `-C incremental`, are never forwarded.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub enum ForwardFlags {
+    /// Forward only the listed flags.
+    Allow(Vec<String>),
+    /// Forward all flags except the listed ones.
+    Deny(Vec<String>),
+}
+
+impl ForwardFlags {
+    pub fn allow<I, S>(flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ForwardFlags::Allow(flags.into_iter().map(Into::into).collect())
+    }
+    pub fn deny<I, S>(flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        ForwardFlags::Deny(flags.into_iter().map(Into::into).collect())
+    }
+    pub(crate) fn forwards(&self, flag: &str) -> bool {
+        match self {
+            ForwardFlags::Allow(flags) => flags.iter().any(|allowed| allowed == flag),
+            ForwardFlags::Deny(flags) => flags.iter().all(|denied| denied != flag),
+        }
+    }
+}
+
+/// By default, only the flags needed to find and configure the crate's dependencies are forwarded.
+impl Default for ForwardFlags {
+    fn default() -> Self {
+        ForwardFlags::allow(["-L", "--cfg", "--extern", "--edition"])
+    }
+}
+
+/// Configuration of the crate build, which the entries are compiled against.
+///
+/// Every distinct configuration is probed with its own `cargo build`, and the result
+/// is cached, so that several batches in one process can use different configurations.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Default)]
+pub struct BuildConfig {
+    features: Features,
+    profile: Option<String>,
+    target: Option<String>,
+    probe: Probe,
+    forward: ForwardFlags,
+    runner: Vec<String>,
+    linker: Option<String>,
+}
+
+impl BuildConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_features(self, features: Features) -> Self {
+        Self { features, ..self }
+    }
+    pub fn features(&self) -> &Features {
+        &self.features
+    }
+    /// Build the crate with the given cargo profile, e.g. `"release"`.
+    ///
+    /// By default, the profile is chosen to match the one the tests are built with.
+    pub fn with_profile(self, profile: impl Into<String>) -> Self {
+        Self {
+            profile: Some(profile.into()),
+            ..self
+        }
+    }
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+    /// Build the crate for the given target triple instead of the host one.
+    pub fn with_target(self, target: impl Into<String>) -> Self {
+        Self {
+            target: Some(target.into()),
+            ..self
+        }
+    }
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+    pub fn with_probe(self, probe: Probe) -> Self {
+        Self { probe, ..self }
+    }
+    pub fn probe(&self) -> Probe {
+        self.probe
+    }
+    /// Select the flags forwarded from the captured command; only used with `Probe::SourceBin`.
+    pub fn with_forwarded_flags(self, forward: ForwardFlags) -> Self {
+        Self { forward, ..self }
+    }
+    pub fn forwarded_flags(&self) -> &ForwardFlags {
+        &self.forward
+    }
+    /// Run the built entries through the given command, like cargo's `target.<triple>.runner`.
+    ///
+    /// The first item is the program, and the rest are its arguments; the path to the entry
+    /// and the entry's own arguments are appended to them. This is mostly useful together with
+    /// `with_target`, e.g. to run the entries under `qemu-aarch64`. If no runner is set,
+    /// the one from `CARGO_TARGET_<TRIPLE>_RUNNER` environment variable is used for the target;
+    /// the cargo configuration files are not read.
+    pub fn with_runner<I, S>(self, runner: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            runner: runner.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+    pub fn runner(&self) -> &[String] {
+        &self.runner
+    }
+    /// Link the entries with the given program, like cargo's `target.<triple>.linker`.
+    ///
+    /// If no linker is set, the one from `CARGO_TARGET_<TRIPLE>_LINKER` environment variable
+    /// is used for the target; the cargo configuration files are not read.
+    pub fn with_linker(self, linker: impl Into<String>) -> Self {
+        Self {
+            linker: Some(linker.into()),
+            ..self
+        }
+    }
+    pub fn linker(&self) -> Option<&str> {
+        self.linker.as_deref()
+    }
+    /// The runner set for this configuration, or the one cargo would use for the target.
+    pub(crate) fn target_runner(&self) -> Vec<String> {
+        if !self.runner.is_empty() {
+            return self.runner.clone();
+        }
+        self.target_var("RUNNER")
+            .map(|runner| runner.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+    /// The linker set for this configuration, or the one cargo would use for the target.
+    pub(crate) fn target_linker(&self) -> Option<String> {
+        self.linker.clone().or_else(|| self.target_var("LINKER"))
+    }
+    /// The `CARGO_TARGET_<TRIPLE>_<KEY>` environment variable for the target, if it is set.
+    fn target_var(&self, key: &str) -> Option<String> {
+        let target = self.target.as_ref()?;
+        let var = format!(
+            "CARGO_TARGET_{}_{}",
+            target.to_uppercase().replace(['-', '.'], "_"),
+            key
+        );
+        env::var(var).ok()
+    }
+}
+
+fn jobs_env() -> BatchResult<usize> {
+    let var = match env::var_os("BATCH_RUN_JOBS") {
+        Some(var) => var,
+        None => return Ok(1),
+    };
+
+    match var.as_os_str().to_str().map(str::parse::<usize>) {
+        Some(Ok(jobs)) if jobs > 0 => Ok(jobs),
+        _ => Err(BatchError::ConfigError(ConfigError::JobsEnvVar(var))),
+    }
+}
+
+fn timeout_env() -> BatchResult<Option<Duration>> {
+    let var = match env::var_os("BATCH_RUN_TIMEOUT") {
+        Some(var) => var,
+        None => return Ok(None),
+    };
+
+    match var.as_os_str().to_str().map(str::parse::<u64>) {
+        Some(Ok(secs)) if secs > 0 => Ok(Some(Duration::from_secs(secs))),
+        _ => Err(BatchError::ConfigError(ConfigError::TimeoutEnvVar(var))),
+    }
+}
+
+pub struct WriterBuilder<W: WriteColor>(Arc<dyn Fn() -> W + Send + Sync>);
 impl<W: WriteColor> Clone for WriterBuilder<W> {
     fn clone(&self) -> Self {
-        Self(Rc::clone(&self.0))
+        Self(Arc::clone(&self.0))
     }
 }
 
 impl<W: WriteColor> WriterBuilder<W> {
-    pub fn new(inner: Box<dyn Fn() -> W>) -> Self
+    /// The closure is shared between the worker threads (see `Config::with_jobs`),
+    /// so it has to be `Send + Sync`.
+    pub fn new(inner: Box<dyn Fn() -> W + Send + Sync>) -> Self
     where
         W: 'static,
     {
-        Self(Rc::new(inner))
+        Self(Arc::from(inner))
     }
     pub(crate) fn build(&self) -> W {
         self.0()
@@ -49,17 +325,49 @@ impl<W: WriteColor> WriterBuilder<W> {
 }
 impl Default for WriterBuilder<StandardStream> {
     fn default() -> Self {
-        Self(Rc::new(|| StandardStream::stderr(ColorChoice::Always)))
+        Self(Arc::new(|| StandardStream::stderr(ColorChoice::Always)))
     }
 }
 impl WriterBuilder<Buffer> {
     pub fn buffer() -> Self {
-        Self(Rc::new(crate::term::buf))
+        Self(Arc::new(crate::term::buf))
+    }
+}
+
+/// Flags of the test harness which take the value as the next argument.
+const FLAGS_WITH_VALUE: &[&str] = &[
+    "--test-threads",
+    "--format",
+    "--logfile",
+    "--skip",
+    "--color",
+    "--shuffle-seed",
+    "-Z",
+];
+
+fn filter_env() -> BatchResult<Vec<String>> {
+    let var = match env::var_os("BATCH_RUN_FILTER") {
+        Some(var) => var,
+        None => return Ok(Vec::new()),
+    };
+
+    match var.to_str() {
+        Some("") => Ok(Vec::new()),
+        Some(filter) => Ok(vec![filter.to_owned()]),
+        None => Err(BatchError::ConfigError(ConfigError::FilterEnvVar(var))),
     }
 }
 
 pub struct Config<W: WriteColor> {
     update_mode: Update,
+    jobs: usize,
+    timeout: Option<Duration>,
+    mismatch_view: MismatchView,
+    edition: Option<Edition>,
+    build: BuildConfig,
+    filter: Vec<String>,
+    events: Option<Events>,
+    reporter: Arc<dyn Reporter>,
     writer: WriterBuilder<W>,
 }
 
@@ -67,6 +375,14 @@ impl Default for Config<StandardStream> {
     fn default() -> Self {
         Self {
             update_mode: Default::default(),
+            jobs: 1,
+            timeout: None,
+            mismatch_view: Default::default(),
+            edition: None,
+            build: BuildConfig::default(),
+            filter: Vec::new(),
+            events: None,
+            reporter: Arc::new(ColoredReporter),
             writer: Default::default(),
         }
     }
@@ -76,6 +392,14 @@ impl Config<StandardStream> {
     pub fn from_env() -> BatchResult<Self> {
         Ok(Self {
             update_mode: Update::env()?,
+            jobs: jobs_env()?,
+            timeout: timeout_env()?,
+            mismatch_view: Default::default(),
+            edition: None,
+            build: BuildConfig::default(),
+            filter: filter_env()?,
+            events: None,
+            reporter: Arc::new(ColoredReporter),
             writer: WriterBuilder::default(),
         })
     }
@@ -90,15 +414,169 @@ impl<W: WriteColor> Config<W> {
     pub fn update_mode(&self) -> Update {
         self.update_mode
     }
+    /// Set the number of entries to be built and run simultaneously.
+    ///
+    /// Results are reported in the same order as with the single job. Note, however, that
+    /// with the default writer every entry logs directly to stderr, so the logs of entries
+    /// running at the same time may be interleaved; use `with_buffer` to avoid this.
+    ///
+    /// `Config::from_env` takes the number of jobs from `BATCH_RUN_JOBS` environment variable.
+    pub fn with_jobs(self, jobs: usize) -> Self {
+        Self {
+            jobs: jobs.max(1),
+            ..self
+        }
+    }
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+    /// Set the default time limit for running the entries.
+    ///
+    /// The process of the entry which exceeds it is killed, together with the processes
+    /// it has started. Every entry can override this limit with `EntryBuilder::timeout`.
+    ///
+    /// `Config::from_env` takes the limit in seconds from `BATCH_RUN_TIMEOUT` environment variable.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+    pub fn with_mismatch_view(self, mismatch_view: MismatchView) -> Self {
+        Self {
+            mismatch_view,
+            ..self
+        }
+    }
+    pub fn mismatch_view(&self) -> MismatchView {
+        self.mismatch_view
+    }
+    /// Build all entries with the given Rust edition instead of the one used by the crate.
+    ///
+    /// The edition set for the single entry, either with `EntryBuilder::edition`
+    /// or with the header comment, takes precedence.
+    pub fn with_edition(self, edition: Edition) -> Self {
+        Self {
+            edition: Some(edition),
+            ..self
+        }
+    }
+    pub fn edition(&self) -> Option<Edition> {
+        self.edition
+    }
+    /// Build all entries against the crate compiled with the given features.
+    ///
+    /// The features set for the single entry with `EntryBuilder::features` replace these ones.
+    pub fn with_features(mut self, features: Features) -> Self {
+        self.build.features = features;
+        self
+    }
+    pub fn features(&self) -> &Features {
+        self.build.features()
+    }
+    /// Build the crate the entries are compiled against with the given configuration.
+    ///
+    /// This replaces the features set with `with_features`.
+    pub fn with_build(self, build: BuildConfig) -> Self {
+        Self { build, ..self }
+    }
+    pub fn build(&self) -> &BuildConfig {
+        &self.build
+    }
+    /// Run only the entries whose path contains the provided pattern.
+    ///
+    /// If called several times, the entry is run when it matches any of the patterns.
+    /// The other entries are not run, but they are still reported as skipped.
+    pub fn with_filter(mut self, pattern: impl Into<String>) -> Self {
+        self.filter.push(pattern.into());
+        self
+    }
+    /// Take the filter patterns from the command line arguments, ignoring the flags.
+    ///
+    /// This is intended for the test targets with `harness = false`, since otherwise
+    /// the arguments are also used by the test harness to filter the tests themselves.
+    /// See `with_filter_from` for the way the arguments are handled.
+    pub fn with_filter_from_args(self) -> Self {
+        self.with_filter_from(env::args().skip(1))
+    }
+    /// Take the filter patterns from the provided arguments, ignoring the flags.
+    ///
+    /// The values of the test harness flags which take one (e.g. `--test-threads 4`)
+    /// are ignored too. Every argument after `--` is taken as a pattern.
+    pub fn with_filter_from<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                return args.fold(self, Self::with_filter);
+            }
+            if FLAGS_WITH_VALUE.contains(&arg.as_str()) {
+                args.next();
+            } else if !arg.starts_with('-') {
+                self = self.with_filter(arg);
+            }
+        }
+        self
+    }
+    pub fn filter(&self) -> &[String] {
+        &self.filter
+    }
+    pub(crate) fn is_filtered_out(&self, name: &str) -> bool {
+        !self.filter.is_empty() && !self.filter.iter().any(|pattern| name.contains(pattern))
+    }
+    /// Write the progress of the batch as newline-delimited JSON events to the provided output.
+    ///
+    /// This is independent of the human-readable output, which is still written to the writer.
+    pub fn with_events(self, out: impl Write + Send + 'static) -> Self {
+        Self {
+            events: Some(Events::new(out)),
+            ..self
+        }
+    }
+    pub(crate) fn events(&self) -> Option<&Events> {
+        self.events.as_ref()
+    }
+    /// Replace the default colored output with the custom reporter.
+    pub fn with_reporter(self, reporter: impl Reporter + 'static) -> Self {
+        Self {
+            reporter: Arc::new(reporter),
+            ..self
+        }
+    }
+    pub fn reporter(&self) -> &dyn Reporter {
+        &*self.reporter
+    }
     pub fn with_writer<W2: WriteColor>(self, writer: WriterBuilder<W2>) -> Config<W2> {
         Config {
             writer,
             update_mode: self.update_mode,
+            jobs: self.jobs,
+            timeout: self.timeout,
+            mismatch_view: self.mismatch_view,
+            edition: self.edition,
+            build: self.build,
+            filter: self.filter,
+            events: self.events,
+            reporter: self.reporter,
         }
     }
     pub fn with_buffer(self) -> Config<Buffer> {
         Config {
             update_mode: self.update_mode,
+            jobs: self.jobs,
+            timeout: self.timeout,
+            mismatch_view: self.mismatch_view,
+            edition: self.edition,
+            build: self.build,
+            filter: self.filter,
+            events: self.events,
+            reporter: self.reporter,
             writer: WriterBuilder::buffer(),
         }
     }
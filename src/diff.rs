@@ -0,0 +1,239 @@
+//! Line-based diff, used to show the mismatches between expected and actual outputs.
+//!
+//! The outputs we compare are usually small and differ only in a few lines,
+//! so the simple LCS table is more than enough here; the common prefix and suffix
+//! are cut off before building it, to keep the table small in the typical case.
+
+use std::fmt;
+
+/// Number of unchanged lines shown around every change.
+const CONTEXT: usize = 3;
+/// Maximal size of the LCS table; if the changed parts are larger, they are shown
+/// as completely replaced, since there's hardly any sense in the detailed diff anyway.
+const MAX_TABLE_SIZE: usize = 4_000_000;
+
+/// Single step of the edit script transforming one sequence into another.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Op<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Line of the diff hunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// The line is present in both outputs.
+    Context(String),
+    /// The line is present only in the expected output.
+    Removed(String),
+    /// The line is present only in the actual output.
+    Added(String),
+}
+
+/// Group of changed lines, together with some unchanged context around them.
+///
+/// Line numbers are 1-based, as in the unified diff format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub expected_start: usize,
+    pub expected_len: usize,
+    pub actual_start: usize,
+    pub actual_len: usize,
+    pub lines: Vec<Line>,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Context(line) => write!(f, " {}", line),
+            Line::Removed(line) => write!(f, "-{}", line),
+            Line::Added(line) => write!(f, "+{}", line),
+        }
+    }
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "@@ -{},{} +{},{} @@",
+            self.expected_start, self.expected_len, self.actual_start, self.actual_len
+        )?;
+        self.lines
+            .iter()
+            .try_for_each(|line| writeln!(f, "{}", line))
+    }
+}
+
+/// Build the edit script for two sequences of strings.
+pub fn diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let mut ops: Vec<_> = old[..prefix].iter().map(|line| Op::Equal(line)).collect();
+    ops.extend(lcs_diff(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    ));
+    ops.extend(old[old.len() - suffix..].iter().map(|line| Op::Equal(line)));
+    ops
+}
+
+fn lcs_diff<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let (n, m) = (old.len(), new.len());
+    if (n + 1) * (m + 1) > MAX_TABLE_SIZE {
+        return old
+            .iter()
+            .map(|line| Op::Delete(line))
+            .chain(new.iter().map(|line| Op::Insert(line)))
+            .collect();
+    }
+
+    // table[i][j] is the length of LCS for old[i..] and new[j..]
+    let mut table = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i][j] = if old[i] == new[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(Op::Equal(old[i]));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            ops.push(Op::Delete(old[i]));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| Op::Delete(line)));
+    ops.extend(new[j..].iter().map(|line| Op::Insert(line)));
+    ops
+}
+
+/// Compute the hunks for the line-by-line difference of two texts.
+pub fn hunks(expected: &str, actual: &str) -> Vec<Hunk> {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    line_hunks(&expected, &actual)
+}
+
+/// Compute the hunks for the difference of two texts, already split into lines.
+pub fn line_hunks(expected: &[&str], actual: &[&str]) -> Vec<Hunk> {
+    let ops = diff(expected, actual);
+
+    // first, find the ranges of operations to be shown, merging the ones which overlap
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (index, op) in ops.iter().enumerate() {
+        if let Op::Equal(_) = op {
+            continue;
+        }
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + CONTEXT + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    // then, convert them to the hunks, tracking the line numbers on both sides
+    let mut hunks = Vec::with_capacity(ranges.len());
+    let (mut expected_line, mut actual_line, mut position) = (1, 1, 0);
+    for (start, end) in ranges {
+        for op in &ops[position..start] {
+            let (e, a) = advance(op);
+            expected_line += e;
+            actual_line += a;
+        }
+        let mut hunk = Hunk {
+            expected_start: expected_line,
+            expected_len: 0,
+            actual_start: actual_line,
+            actual_len: 0,
+            lines: Vec::with_capacity(end - start),
+        };
+        for op in &ops[start..end] {
+            let (e, a) = advance(op);
+            hunk.expected_len += e;
+            hunk.actual_len += a;
+            hunk.lines.push(match *op {
+                Op::Equal(line) => Line::Context(line.to_owned()),
+                Op::Delete(line) => Line::Removed(line.to_owned()),
+                Op::Insert(line) => Line::Added(line.to_owned()),
+            });
+        }
+        expected_line += hunk.expected_len;
+        actual_line += hunk.actual_len;
+        position = end;
+        hunks.push(hunk);
+    }
+    hunks
+}
+
+fn advance(op: &Op<'_>) -> (usize, usize) {
+    match op {
+        Op::Equal(_) => (1, 1),
+        Op::Delete(_) => (1, 0),
+        Op::Insert(_) => (0, 1),
+    }
+}
+
+/// Split the line into words, punctuation and whitespace, for the word-level diff.
+pub fn words(line: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    fn class(c: char) -> Class {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut prev: Option<Class> = None;
+    for (index, c) in line.char_indices() {
+        let current = class(c);
+        // punctuation is always split into the single characters
+        let split = match &prev {
+            Some(prev) => *prev != current || current == Class::Other,
+            None => false,
+        };
+        if split {
+            words.push(&line[start..index]);
+            start = index;
+        }
+        prev = Some(current);
+    }
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
@@ -1,39 +1,66 @@
+use serde::Serialize;
 use termcolor::WriteColor;
 
-use std::fs::File;
+use std::collections::BTreeMap;
+use std::fs::{read, File};
 use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::{Duration, Instant};
 
 use crate::binary::BinaryBuilder;
-use crate::cargo_rustc;
-use crate::config::{Config, WriterBuilder};
-use crate::logging;
+use crate::cargo_rustc::{self, in_manifest_dir, manifest_dir, Artifact, Emit};
+use crate::config::{Config, Edition, Features};
+use crate::events::EntryFinished;
+use crate::header::Header;
+use crate::mismatch::LocalOutput;
 use crate::normalize::diagnostics;
 use crate::result::{
-    error::{EntryError, EntryFailed},
+    error::{EntryError, EntryFailed, NoExpected},
     EntryOutput, EntryResult,
 };
-use crate::snapshot::{check_compile_fail, check_run_match};
+use crate::snapshot::{check_compile_fail, check_run_match, check_warnings, wip_path};
+use crate::status::Status;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize)]
 pub enum Expected {
     RunMatch,
+    RunPass,
+    RunFail,
+    CompilePass,
     CompileFail,
 }
 
 impl Expected {
-    pub fn is_run_pass(self) -> bool {
+    pub fn should_compile(self) -> bool {
         use Expected::*;
         match self {
-            RunMatch => true,
+            RunMatch | RunPass | RunFail | CompilePass => true,
             CompileFail => false,
         }
     }
+    #[deprecated(note = "renamed to `should_compile`")]
+    pub fn is_run_pass(self) -> bool {
+        self.should_compile()
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Entry {
     path: PathBuf,
     expected: Expected,
+    timeout: Option<Duration>,
+    stdin: Option<Vec<u8>>,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    exit_code: Option<i32>,
+    edition: Option<Edition>,
+    features: Option<Features>,
+    snapshot_dir: Option<PathBuf>,
+    link: bool,
+    snapshot_warnings: bool,
+    panic_message: Option<String>,
+    ignore: Option<String>,
+    compile_flags: Vec<String>,
 }
 
 impl Entry {
@@ -41,52 +68,284 @@ impl Entry {
         Self {
             path: path.as_ref().to_owned(),
             expected,
+            timeout: None,
+            stdin: None,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            exit_code: None,
+            edition: None,
+            features: None,
+            snapshot_dir: None,
+            link: false,
+            snapshot_warnings: false,
+            panic_message: None,
+            ignore: None,
+            compile_flags: Vec::new(),
         }
     }
 
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    pub fn set_stdin(&mut self, stdin: Vec<u8>) {
+        self.stdin = Some(stdin);
+    }
+
+    pub fn add_arg(&mut self, arg: String) {
+        self.args.push(arg);
+    }
+
+    pub fn set_env(&mut self, key: String, value: String) {
+        self.env.insert(key, value);
+    }
+
+    pub fn set_exit_code(&mut self, code: i32) {
+        self.exit_code = Some(code);
+    }
+
+    pub fn set_edition(&mut self, edition: Edition) {
+        self.edition = Some(edition);
+    }
+
+    pub fn set_features(&mut self, features: Features) {
+        self.features = Some(features);
+    }
+
+    pub fn set_snapshot_dir(&mut self, dir: PathBuf) {
+        self.snapshot_dir = Some(dir);
+    }
+
+    pub fn set_link(&mut self, link: bool) {
+        self.link = link;
+    }
+
+    pub fn set_snapshot_warnings(&mut self, snapshot_warnings: bool) {
+        self.snapshot_warnings = snapshot_warnings;
+    }
+
+    pub fn set_panic_message(&mut self, pattern: String) {
+        self.panic_message = Some(pattern);
+    }
+
+    pub fn set_ignore(&mut self, reason: String) {
+        self.ignore = Some(reason);
+    }
+
     fn run<W: WriteColor>(
         &self,
         builder: &BinaryBuilder,
         cfg: &Config<W>,
-        log: &mut impl WriteColor,
+        log: &mut dyn WriteColor,
     ) -> EntryResult<()> {
-        logging::log_entry_start(self, log)?;
+        let reporter = cfg.reporter();
+        reporter.entry_started(log, &self.path, self.expected)?;
         self.try_open()?;
 
-        let mut output =
-            cargo_rustc::build_entry(builder, &self.path, self.expected.is_run_pass())?;
+        let artifact = Artifact::new()?;
+        let output = cargo_rustc::build_entry(
+            builder,
+            &self.path,
+            self.edition.or(cfg.edition()),
+            &self.compile_flags,
+            self.emit(),
+            &artifact,
+        )?;
 
-        let check = match self.expected {
+        // early exit if the entry should have compiled, but it has not
+        if self.expected.should_compile() && !output.status.success() {
+            let stderr = diagnostics(&output.stderr).preferred().to_owned();
+            reporter.unexpected_build_error(log, &stderr)?;
+            return Err(EntryFailed::ShouldCompile(stderr));
+        }
+
+        let res = match self.expected {
             Expected::RunMatch => {
-                // early exit if the entry has not compiled
-                if !output.status.success() {
-                    let stderr = diagnostics(&output.stderr).preferred().to_owned();
-                    logging::unexpected_build_error(log, stderr.as_bytes())?;
-                    return Err(EntryFailed::ShouldCompile(stderr));
+                let stdin = self.stdin()?;
+                let output = self.execute(builder, &artifact, stdin.as_deref(), cfg, log)?;
+                let output = LocalOutput::from(output);
+                let output = output.with_input(&self.args, &self.env, stdin.as_deref());
+                check_run_match(&self.expected_path("snapshot"), output, cfg, log)
+            }
+            Expected::RunPass => {
+                let output =
+                    self.execute(builder, &artifact, self.stdin()?.as_deref(), cfg, log)?;
+                // the explicitly provided exit code was already checked
+                if self.exit_code.is_none() && !output.status.success() {
+                    let status = Status::from(output.status);
+                    let stderr_tail = tail(&output.stderr);
+                    reporter.unexpected_run_failure(log, &status, &stderr_tail)?;
+                    return Err(EntryFailed::RunFailed {
+                        status,
+                        stderr_tail,
+                    });
+                }
+                Ok(())
+            }
+            Expected::RunFail => {
+                let output =
+                    self.execute(builder, &artifact, self.stdin()?.as_deref(), cfg, log)?;
+                if output.status.success() {
+                    reporter.unexpected_run_success(log)?;
+                    return Err(EntryFailed::ShouldFail);
+                }
+                match &self.panic_message {
+                    Some(expected) if !panicked_with(&output.stderr, expected) => {
+                        let stderr_tail = tail(&output.stderr);
+                        reporter.panic_mismatch(log, expected, &stderr_tail)?;
+                        Err(EntryFailed::PanicMismatch {
+                            expected: expected.clone(),
+                            stderr_tail,
+                        })
+                    }
+                    _ => Ok(()),
                 }
-                output = cargo_rustc::run_entry()?;
-                check_run_match
             }
-            Expected::CompileFail => check_compile_fail,
+            Expected::CompilePass if self.snapshot_warnings => {
+                check_warnings(&self.expected_path("stderr"), output, cfg, log)
+            }
+            Expected::CompilePass => Ok(()),
+            Expected::CompileFail => {
+                check_compile_fail(&self.expected_path("stderr"), output, cfg, log)
+            }
         };
-        check(&self.path, output, cfg.update_mode(), log)
-            .and_then(|_| logging::ok(log).map_err(Into::into))
+        res.and_then(|_| reporter.ok(log).map_err(Into::into))
+    }
+
+    /// Run the built entry, checking the exit code if it was provided.
+    fn execute<W: WriteColor>(
+        &self,
+        builder: &BinaryBuilder,
+        artifact: &Artifact,
+        stdin: Option<&[u8]>,
+        cfg: &Config<W>,
+        log: &mut dyn WriteColor,
+    ) -> EntryResult<Output> {
+        let timeout = self.timeout.or_else(|| cfg.timeout());
+        let output = match cargo_rustc::run_entry(
+            artifact,
+            builder.runner(),
+            &self.args,
+            &self.env,
+            stdin,
+            timeout,
+        ) {
+            Err(EntryFailed::Timeout(timeout)) => {
+                cfg.reporter().timeout(log, timeout)?;
+                return Err(EntryFailed::Timeout(timeout));
+            }
+            output => output?,
+        };
+        if let Some(expected) = self.exit_code {
+            let actual = Status::from(output.status);
+            if actual.code() != Some(expected) {
+                cfg.reporter().exit_code_mismatch(log, expected, &actual)?;
+                return Err(EntryFailed::ExitCodeMismatch { expected, actual });
+            }
+        }
+        Ok(output)
+    }
+
+    fn emit(&self) -> Emit {
+        match self.expected {
+            Expected::RunMatch | Expected::RunPass | Expected::RunFail => Emit::Link,
+            Expected::CompilePass if self.link => Emit::Link,
+            Expected::CompilePass => Emit::Metadata,
+            Expected::CompileFail => Emit::DepInfo,
+        }
+    }
+
+    /// Path to the file with expected output, either adjacent to the entry
+    /// or in the configured snapshot directory.
+    fn expected_path(&self, extension: &str) -> PathBuf {
+        let path = self.path.with_extension(extension);
+        match (&self.snapshot_dir, path.file_name()) {
+            (Some(dir), Some(name)) => dir.join(name),
+            _ => path,
+        }
+    }
+
+    /// Path to the snapshot checked by this entry, if there is any.
+    fn snapshot_path(&self) -> Option<PathBuf> {
+        match self.expected {
+            Expected::RunMatch => Some(self.expected_path("snapshot")),
+            Expected::CompilePass if self.snapshot_warnings => Some(self.expected_path("stderr")),
+            Expected::CompileFail => Some(self.expected_path("stderr")),
+            Expected::RunPass | Expected::RunFail | Expected::CompilePass => None,
+        }
+    }
+
+    /// Read the header directives of the entry.
+    ///
+    /// If the entry can't be read, the header is empty, so that the error is reported when it's run.
+    fn read_header(&self) -> EntryResult<Header> {
+        match std::fs::read_to_string(in_manifest_dir(&self.path)) {
+            Ok(source) => Header::parse(&source).map_err(Into::into),
+            Err(_) => Ok(Header::default()),
+        }
+    }
+
+    /// Apply the header directives; the settings provided explicitly take precedence.
+    fn with_header(mut self, header: Header) -> Self {
+        self.edition = self.edition.or(header.edition);
+        self.exit_code = self.exit_code.or(header.exit_status);
+        if self.args.is_empty() {
+            self.args = header.run_args;
+        }
+        self.compile_flags = header.compile_flags;
+        self.ignore = self.ignore.or(header.ignore);
+        self
+    }
+
+    /// Input for the entry process: either provided explicitly, or read from the adjacent
+    /// _*.stdin_ file, if it exists.
+    fn stdin(&self) -> EntryResult<Option<Vec<u8>>> {
+        if self.stdin.is_some() {
+            return Ok(self.stdin.clone());
+        }
+        let stdin_path = self.path.with_extension("stdin");
+        if !in_manifest_dir(&stdin_path).exists() {
+            return Ok(None);
+        }
+        read(in_manifest_dir(&stdin_path))
+            .map(Some)
+            .map_err(|err| EntryError::Open(stdin_path, err).into())
     }
 
     fn try_open(&self) -> EntryResult<()> {
-        if self.path.exists() {
+        let path = in_manifest_dir(&self.path);
+        if path.exists() {
             return Ok(());
         }
-        match File::open(&self.path) {
+        match File::open(path) {
             Ok(_) => Ok(()),
             Err(err) => Err(EntryError::Open(self.path.clone(), err).into()),
         }
     }
-    pub fn path(&self) -> &Path {
-        &self.path
+
+    pub(crate) fn features(&self) -> Option<&Features> {
+        self.features.as_ref()
     }
-    pub fn expected(&self) -> Expected {
-        self.expected
+}
+
+/// Number of the last stderr lines kept when the entry fails to run.
+const STDERR_TAIL_LINES: usize = 20;
+
+fn tail(stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let lines: Vec<_> = stderr.lines().collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+}
+
+/// Check if the process has panicked with the message containing the pattern.
+///
+/// The format of the panic report differs between Rust versions, so we don't try to parse it
+/// and simply search for the pattern anywhere after the panic header.
+fn panicked_with(stderr: &[u8], pattern: &str) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    match stderr.find(" panicked at ") {
+        Some(start) => stderr[start..].contains(pattern),
+        None => false,
     }
 }
 
@@ -94,26 +353,46 @@ pub struct ExpandedEntry<W: WriteColor> {
     log: W,
     raw_entry: Entry,
     error: Option<EntryFailed>,
+    skipped: bool,
 }
 
 pub(crate) fn expand_globs<W: WriteColor>(
     entries: &[Entry],
-    writer: &WriterBuilder<W>,
+    cfg: &Config<W>,
 ) -> Vec<ExpandedEntry<W>> {
     fn glob(pattern: &str) -> EntryResult<Vec<PathBuf>> {
-        let mut paths = glob::glob(pattern)?
-            .map(|entry| entry.map_err(EntryFailed::from))
+        // the pattern is resolved against the crate directory, but the entries keep relative paths
+        let dir = manifest_dir();
+        let pattern = if Path::new(pattern).is_absolute() {
+            pattern.to_owned()
+        } else {
+            format!(
+                "{}/{}",
+                glob::Pattern::escape(&dir.to_string_lossy()),
+                pattern
+            )
+        };
+        let mut paths = glob::glob(&pattern)?
+            .map(|entry| {
+                let path = entry.map_err(EntryFailed::from)?;
+                Ok(match path.strip_prefix(&dir) {
+                    Ok(relative) => relative.to_owned(),
+                    Err(_) => path,
+                })
+            })
             .collect::<EntryResult<Vec<PathBuf>>>()?;
         paths.sort();
         Ok(paths)
     }
 
+    let writer = cfg.writer();
     let mut vec = Vec::new();
 
     for entry in entries {
         let mut expanded = ExpandedEntry {
             raw_entry: entry.clone(),
             error: None,
+            skipped: false,
             log: writer.build(),
         };
         if let Some(utf8) = entry.path.to_str() {
@@ -124,9 +403,10 @@ pub(crate) fn expand_globs<W: WriteColor>(
                             vec.push(ExpandedEntry {
                                 raw_entry: Entry {
                                     path,
-                                    expected: expanded.raw_entry.expected,
+                                    ..expanded.raw_entry.clone()
                                 },
                                 error: None,
+                                skipped: false,
                                 log: writer.build(),
                             });
                         }
@@ -139,6 +419,9 @@ pub(crate) fn expand_globs<W: WriteColor>(
         vec.push(expanded);
     }
 
+    for expanded in &mut vec {
+        expanded.skipped = cfg.is_filtered_out(&expanded.name());
+    }
     vec
 }
 
@@ -148,19 +431,94 @@ impl<W: WriteColor> ExpandedEntry<W> {
             error,
             raw_entry,
             mut log,
+            ..
         } = self;
+        let (raw_entry, error) = match error {
+            Some(error) => (raw_entry, Some(error)),
+            None => match raw_entry.read_header() {
+                Ok(header) => (raw_entry.with_header(header), None),
+                Err(error) => (raw_entry, Some(error)),
+            },
+        };
+        let events = cfg.events();
+        if error.is_none() {
+            if let Some(reason) = raw_entry.ignore.clone() {
+                // the entry is not run, so the failures to report this are silenced
+                let _ = cfg.reporter().entry_ignored(
+                    &mut log,
+                    &raw_entry.path,
+                    raw_entry.expected,
+                    &reason,
+                );
+                if let Some(events) = events {
+                    let _ = events.entry_ignored(&raw_entry.path, raw_entry.expected, &reason);
+                }
+                return EntryOutput::ignored(log, reason);
+            }
+        }
+        // events are auxiliary, so the failure to write them must not affect the entry itself
+        if let Some(events) = events {
+            let _ = events.entry_started(&raw_entry.path, raw_entry.expected);
+        }
+        let start = Instant::now();
         let res = match error {
             None => raw_entry.run(builder, cfg, &mut log),
             Some(error) => {
                 // explicitly silence the io::Error - we have another error to show up
-                let _ = logging::log_entry_fail_to_start(&raw_entry, &mut log);
+                let _ = cfg
+                    .reporter()
+                    .entry_fail_to_start(&mut log, &raw_entry.path);
                 Err(error)
             }
         };
-        EntryOutput::new(res, log)
+        let duration = start.elapsed();
+        // the result is already known, so the failure to report it is silenced, too
+        let _ = cfg
+            .reporter()
+            .entry_finished(&mut log, &raw_entry.path, &res, duration);
+        if let Some(events) = events {
+            let snapshot = raw_entry.snapshot_path();
+            let wip = match (&res, &snapshot) {
+                (Err(EntryFailed::ExpectedNotExist(NoExpected::ToWip(_))), Some(snapshot)) => {
+                    Some(wip_path(snapshot))
+                }
+                _ => None,
+            };
+            let _ = events.entry_finished(EntryFinished {
+                path: &raw_entry.path,
+                expected: raw_entry.expected,
+                res: &res,
+                duration,
+                snapshot,
+                wip,
+            });
+        }
+        EntryOutput::new(res, log, duration)
+    }
+
+    /// Skip the entry, only reporting that it was filtered out.
+    pub fn skip(self, cfg: &Config<W>) -> EntryOutput<W> {
+        let Self {
+            raw_entry, mut log, ..
+        } = self;
+        // nothing was run, so there's nothing to fail, either
+        let _ = cfg.reporter().entry_skipped(&mut log, &raw_entry.path);
+        if let Some(events) = cfg.events() {
+            let _ = events.entry_skipped(&raw_entry.path, raw_entry.expected);
+        }
+        EntryOutput::skipped(log)
+    }
+
+    /// Name of the entry, as used in the results.
+    pub fn name(&self) -> String {
+        self.raw_entry.path.display().to_string()
+    }
+
+    pub fn is_skipped(&self) -> bool {
+        self.skipped
     }
 
-    pub fn path(&self) -> &Path {
-        &self.raw_entry.path
+    pub(crate) fn features(&self) -> Option<&Features> {
+        self.raw_entry.features()
     }
 }
@@ -0,0 +1,127 @@
+//! Machine-readable stream of batch progress, written as newline-delimited JSON.
+
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::entry::Expected;
+use crate::result::{EntryResult, Summary};
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    BatchStarted {
+        entries: usize,
+    },
+    EntryStarted {
+        path: &'a Path,
+        expected: Expected,
+    },
+    EntrySkipped {
+        path: &'a Path,
+        expected: Expected,
+    },
+    EntryIgnored {
+        path: &'a Path,
+        expected: Expected,
+        reason: &'a str,
+    },
+    EntryFinished {
+        path: &'a Path,
+        expected: Expected,
+        /// Either "ok" or the name of `EntryFailed` variant.
+        result: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        /// Duration in seconds.
+        duration: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        snapshot: Option<&'a Path>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        wip: Option<&'a Path>,
+    },
+    BatchFinished {
+        total: usize,
+        passed: usize,
+        failed: usize,
+        skipped: usize,
+        ignored: usize,
+        duration: f64,
+    },
+}
+
+/// Shared writer for the events, which can be used from several workers at once.
+#[derive(Clone)]
+pub(crate) struct Events(Arc<Mutex<dyn Write + Send>>);
+
+pub(crate) struct EntryFinished<'a> {
+    pub path: &'a Path,
+    pub expected: Expected,
+    pub res: &'a EntryResult,
+    pub duration: Duration,
+    pub snapshot: Option<PathBuf>,
+    pub wip: Option<PathBuf>,
+}
+
+impl Events {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(out)))
+    }
+
+    fn emit(&self, event: &Event<'_>) -> io::Result<()> {
+        // every event is written under the single lock, so that the lines are never interleaved
+        let mut out = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        serde_json::to_writer(&mut *out, event)?;
+        writeln!(out)?;
+        out.flush()
+    }
+
+    pub fn batch_started(&self, entries: usize) -> io::Result<()> {
+        self.emit(&Event::BatchStarted { entries })
+    }
+
+    pub fn entry_started(&self, path: &Path, expected: Expected) -> io::Result<()> {
+        self.emit(&Event::EntryStarted { path, expected })
+    }
+
+    pub fn entry_skipped(&self, path: &Path, expected: Expected) -> io::Result<()> {
+        self.emit(&Event::EntrySkipped { path, expected })
+    }
+
+    pub fn entry_ignored(&self, path: &Path, expected: Expected, reason: &str) -> io::Result<()> {
+        self.emit(&Event::EntryIgnored {
+            path,
+            expected,
+            reason,
+        })
+    }
+
+    pub fn entry_finished(&self, entry: EntryFinished<'_>) -> io::Result<()> {
+        let (result, message) = match entry.res {
+            Ok(()) => ("ok", None),
+            Err(err) => (err.kind(), Some(err.to_string())),
+        };
+        self.emit(&Event::EntryFinished {
+            path: entry.path,
+            expected: entry.expected,
+            result,
+            message,
+            duration: entry.duration.as_secs_f64(),
+            snapshot: entry.snapshot.as_deref(),
+            wip: entry.wip.as_deref(),
+        })
+    }
+
+    pub fn batch_finished(&self, summary: &Summary) -> io::Result<()> {
+        self.emit(&Event::BatchFinished {
+            total: summary.total(),
+            passed: summary.passed(),
+            failed: summary.failed(),
+            skipped: summary.skipped(),
+            ignored: summary.ignored(),
+            duration: summary.duration().as_secs_f64(),
+        })
+    }
+}
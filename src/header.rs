@@ -0,0 +1,119 @@
+//! Per-entry configuration, written as the comments at the beginning of the entry,
+//! in the same style as the compiletest headers:
+//!
+//! ```text
+//! // edition:2018
+//! // compile-flags: --cfg 'feature="extra"'
+//! // run-args: --verbose "input file.txt"
+//! // exit-status: 1
+//! // ignore-test
+//! ```
+//!
+//! Only the leading block of comments (possibly interleaved with blank lines) is parsed.
+//! The flags and arguments are split into words with the shell quoting rules.
+
+use crate::config::Edition;
+use crate::result::error::EntryError;
+use crate::rustc_args;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Header {
+    pub edition: Option<Edition>,
+    pub compile_flags: Vec<String>,
+    pub run_args: Vec<String>,
+    pub exit_status: Option<i32>,
+    /// Reason to ignore the entry, either from `ignore-test` or `batch_run: ignore` marker.
+    pub ignore: Option<String>,
+}
+
+impl Header {
+    pub fn parse(source: &str) -> Result<Self, EntryError> {
+        let mut header = Header::default();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let comment = match line.strip_prefix("//") {
+                // doc comments are the part of the code, not the header
+                Some(comment) if !comment.starts_with(['/', '!']) => comment.trim(),
+                _ => break,
+            };
+            header
+                .directive(comment)
+                .map_err(|message| EntryError::Header(index + 1, message))?;
+        }
+
+        // the ignore marker is accepted anywhere in the entry, not only in the header
+        if header.ignore.is_none() {
+            header.ignore = source
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("//"))
+                .find_map(ignore_marker);
+        }
+        Ok(header)
+    }
+
+    fn directive(&mut self, comment: &str) -> Result<(), String> {
+        if comment == "ignore-test" {
+            self.ignore = Some(String::new());
+            return Ok(());
+        }
+        let (name, value) = match comment.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            // ordinary comment
+            None => return Ok(()),
+        };
+        match name {
+            "edition" => {
+                self.edition = Some(parse_edition(value)?);
+            }
+            "compile-flags" => {
+                self.compile_flags.extend(
+                    rustc_args::split(value)
+                        .map_err(|err| format!("malformed {}: {}", name, err))?,
+                );
+            }
+            "run-args" => {
+                self.run_args.extend(
+                    rustc_args::split(value)
+                        .map_err(|err| format!("malformed {}: {}", name, err))?,
+                );
+            }
+            "exit-status" => {
+                let status = value
+                    .parse()
+                    .map_err(|_| format!("expected exit status to be a number, got {:?}", value))?;
+                self.exit_status = Some(status);
+            }
+            // ordinary comment, which happens to contain a colon
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_edition(value: &str) -> Result<Edition, String> {
+    match value {
+        "2015" => Ok(Edition::E2015),
+        "2018" => Ok(Edition::E2018),
+        "2021" => Ok(Edition::E2021),
+        "2024" => Ok(Edition::E2024),
+        _ => Err(format!("unknown edition {:?}", value)),
+    }
+}
+
+/// Parse the `// batch_run: ignore = "reason"` comment; the reason is optional.
+fn ignore_marker(comment: &str) -> Option<String> {
+    let rest = comment.trim().strip_prefix("batch_run:")?.trim_start();
+    let rest = rest.strip_prefix("ignore")?.trim();
+    if rest.is_empty() {
+        return Some(String::new());
+    }
+    let reason = rest.strip_prefix('=')?.trim();
+    let reason = reason
+        .strip_prefix('"')
+        .and_then(|reason| reason.strip_suffix('"'))
+        .unwrap_or(reason);
+    Some(reason.to_owned())
+}
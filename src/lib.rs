@@ -8,14 +8,14 @@
 //! It can be useful when you have a bunch of Rust sources which are not complex enough to be
 //! packed into dedicated crates, but which are (by their meaning) not just integration test cases.
 //! It also checks for output correctness, either on compile-time (for `compile_fail` cases)
-//! or at runtime (for `run_pass` cases).
+//! or at runtime (for `run_match` cases).
 //!
 //! ```toml
 //! [dependencies]
 //! batch_run = "1.0"
 //! ```
 //!
-//! *Compiler support: requires rustc 1.31+*
+//! *Compiler support: requires rustc 1.64+*
 //!
 //! <br>
 //!
@@ -37,7 +37,8 @@
 //! assert that the compiler's error message matches an adjacently named _*.stderr_
 //! file containing the expected output (same file name as the test except with a
 //! different extension). If it doesn't match, the program will print the error message
-//! with expected vs actual compiler output.
+//! with the diff between expected and actual compiler output (use
+//! `Config::with_mismatch_view(MismatchView::Full)` to see both of them in full instead).
 //!
 //! Dependencies listed under `[dependencies]` in the project's Cargo.toml are
 //! accessible from within the batch.
@@ -62,11 +63,108 @@
 //! t.run_match("batches/05-repeat-section.rs");
 //! ```
 //!
+//! If the output is not important, use run_pass instead: such entries are only required
+//! to exit successfully, and no snapshot is written for them. Similarly, run_fail entries
+//! are required to fail at runtime, optionally with the given `.exit_code(..)`
+//! or `.panic_message(..)`.
+//!
+//! <br>
+//!
+//! ## Compile-pass cases
+//!
+//! The compile_pass cases are only built, but not run; any compilation error is a failure.
+//! This is useful for the code which can't be run in the test environment. By default,
+//! the entries are only type-checked; use `.link(true)` to build the executable, and
+//! `.snapshot_warnings(true)` to match the compiler warnings with the _*.stderr_ file.
+//!
 //! <br>
 //!
-//! ## Details
+//! ## Entry settings
+//!
+//! Every method queueing the entries returns an [`EntryBuilder`], which can be used
+//! to adjust the settings of the queued entry:
+//!
+//! ```rust
+//! # use std::time::Duration;
+//! let t = batch_run::Batch::new();
+//! t.run_match("batches/06-read-args.rs")
+//!     .args(vec!["--verbose"])
+//!     .exit_code(1)
+//!     .timeout(Duration::from_secs(10));
+//! t.compile_fail("batches/07-old-edition.rs")
+//!     .edition(batch_run::config::Edition::E2015);
+//! ```
+//!
+//! The same settings can be provided with the comments at the beginning of the entry itself,
+//! in the style of compiletest: `// edition:2018`, `// compile-flags: ...`, `// run-args: ...`,
+//! `// exit-status: N` and `// ignore-test`. The flags and arguments are split into words
+//! with the shell quoting rules.
+//!
+//! The comments work for the entries added with globs, too. The settings provided
+//! with `EntryBuilder` take precedence over them.
+//!
+//! An entry can be disabled temporarily with `EntryBuilder::ignore` or with the
+//! `// batch_run: ignore = "reason"` comment inside it. Such entries are reported as ignored.
+//!
+//! <br>
+//!
+//! ## Build configuration
+//!
+//! The edition for the whole batch is set with `Config::with_edition`. The crate features
+//! the entries are built against are selected with `Config::with_features`
+//! (e.g. `config::Features::all()`).
+//!
+//! The cargo profile and target of the crate build are set with `Config::with_build`.
+//! Every distinct `config::BuildConfig` is probed once per process, so several batches
+//! can check the same entries under different configurations.
+//!
+//! The build is inspected with `cargo metadata` and the JSON messages of `cargo build`,
+//! so nothing is written into the source tree. Only the crate itself and its normal dependencies
+//! are available to the entries, not the dev-dependencies. The `--cfg` values from `RUSTFLAGS`
+//! are passed to the entries, as they are to the crate itself.
+//!
+//! The older approach of adding a temporary binary to `src/bin` is still available
+//! as `config::Probe::SourceBin`. The flags it forwards from the captured `rustc` command
+//! are chosen with `config::ForwardFlags`.
+//!
+//! When cross-compiling with `BuildConfig::with_target`, the built entries can be run
+//! through an emulator with `BuildConfig::with_runner`, like cargo's `target.<triple>.runner`.
+//! The linker is set in the same way with `BuildConfig::with_linker`. By default, they are taken
+//! from `CARGO_TARGET_<TRIPLE>_RUNNER` and `CARGO_TARGET_<TRIPLE>_LINKER` environment variables;
+//! `target.<triple>.runner` and `target.<triple>.linker` from the cargo configuration files
+//! are not read.
+//!
+//! <br>
+//!
+//! ## Running the batch
+//!
+//! To build and run several entries at once, set `BATCH_RUN_JOBS` environment variable
+//! to the number of workers (or use `Config::with_jobs`). The results are still reported
+//! in the order the entries were added. Because of this, `Batch::run_with_config` requires
+//! the writer to be `Send`, and the closure given to `WriterBuilder::new` to be `Send + Sync`.
+//!
+//! The time limit for running every entry can be set in seconds with `BATCH_RUN_TIMEOUT`
+//! (or with `Config::with_timeout`). The entry which exceeds it is killed and reported as failed.
+//!
+//! To run only some of the entries, set `BATCH_RUN_FILTER` environment variable
+//! (or use `Config::with_filter`). Only the entries with the path containing it are run,
+//! and the other ones are reported as skipped.
+//!
+//! <br>
+//!
+//! ## Results
+//!
+//! When the batch is finished, its summary is printed. It is also available programmatically
+//! with `BatchRunResult::summary`.
+//!
+//! The result of the batch can be written as a JUnit XML report, to be picked up by CI,
+//! with `BatchRunResult::write_junit`.
+//!
+//! To follow the progress of the batch programmatically, use `Config::with_events`,
+//! which writes newline-delimited JSON events as the entries are started and finished.
 //!
-//! That's the entire API for now.
+//! The human-readable output itself can be replaced by implementing `reporter::Reporter`
+//! and passing it to `Config::with_reporter`.
 //!
 //! <br>
 //!
@@ -78,15 +176,23 @@
 mod batch;
 mod binary;
 mod cargo_rustc;
+mod diff;
 mod entry;
+mod events;
+mod header;
 mod logging;
 mod mismatch;
 mod normalize;
+mod probe;
 mod runner;
+mod rustc_args;
 mod rustflags;
 mod snapshot;
+mod status;
 mod term;
 
 pub mod config;
+pub mod reporter;
 pub mod result;
-pub use crate::batch::Batch;
+pub use crate::batch::{Batch, EntryBuilder};
+pub use crate::entry::Expected;
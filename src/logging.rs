@@ -4,13 +4,18 @@ use termcolor::{
 };
 use termcolor_output::colored;
 
-use crate::entry::{Entry, Expected};
+use crate::config::MismatchView;
+use crate::diff::{self, Line, Op};
+use crate::entry::Expected;
 use crate::normalize;
+use crate::result::Summary;
+use crate::status::Status;
 
-use std::io;
+use std::io::{self, Write};
 use std::path::Path;
+use std::time::Duration;
 
-pub(crate) fn no_entries(log: &mut impl WriteColor) -> io::Result<()> {
+pub(crate) fn no_entries(mut log: &mut dyn WriteColor) -> io::Result<()> {
     colored!(
         log,
         "{}{}No entries were provided to runner. Maybe the files are not created yet, or the glob path is wrong.\n{}",
@@ -19,30 +24,64 @@ pub(crate) fn no_entries(log: &mut impl WriteColor) -> io::Result<()> {
     Ok(())
 }
 
-pub(crate) fn ok(log: &mut impl WriteColor) -> io::Result<()> {
+pub(crate) fn ok(mut log: &mut dyn WriteColor) -> io::Result<()> {
     colored!(log, "{}ok{}\n", fg!(Some(Green)), reset!())
 }
 
-pub(crate) fn log_entry_start(entry: &Entry, log: &mut impl WriteColor) -> io::Result<()> {
-    let display_name = entry
-        .path()
+/// Separate the batch output from the cargo output before it.
+pub(crate) fn batch_started() -> io::Result<()> {
+    io::stdout().write_all(b"\n\n")
+}
+
+pub(crate) fn skipped(mut log: &mut dyn WriteColor, path: &Path) -> io::Result<()> {
+    let display_name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    write_entry_header(log, &display_name, "")?;
+    colored!(
+        log,
+        "{}skipped{} (filtered out)\n",
+        fg!(Some(Yellow)),
+        reset!()
+    )
+}
+
+pub(crate) fn ignored(mut log: &mut dyn WriteColor, reason: &str) -> io::Result<()> {
+    colored!(log, "{}ignored{}", fg!(Some(Yellow)), reset!())?;
+    if reason.is_empty() {
+        colored!(log, "\n")
+    } else {
+        colored!(log, " ({})\n", reason)
+    }
+}
+
+pub(crate) fn log_entry_start(
+    log: &mut dyn WriteColor,
+    path: &Path,
+    expected: Expected,
+) -> io::Result<()> {
+    let display_name = path
         .file_name()
-        .unwrap_or_else(|| entry.path().as_os_str())
+        .unwrap_or(path.as_os_str())
         .to_string_lossy();
 
-    let expected = match entry.expected() {
+    let expected = match expected {
         Expected::RunMatch => " [should run and generate output]",
+        Expected::RunPass => " [should run successfully]",
+        Expected::RunFail => " [should fail at runtime]",
+        Expected::CompilePass => " [should compile]",
         Expected::CompileFail => " [should fail to compile]",
     };
 
     write_entry_header(log, &display_name, expected)
 }
 
-pub(crate) fn log_entry_fail_to_start(entry: &Entry, buf: &mut impl WriteColor) -> io::Result<()> {
-    write_entry_header(buf, &entry.path().as_os_str().to_string_lossy(), "")
+pub(crate) fn log_entry_fail_to_start(buf: &mut dyn WriteColor, path: &Path) -> io::Result<()> {
+    write_entry_header(buf, &path.as_os_str().to_string_lossy(), "")
 }
 
-fn write_entry_header(buf: &mut impl WriteColor, name: &str, expected: &str) -> io::Result<()> {
+fn write_entry_header(mut buf: &mut dyn WriteColor, name: &str, expected: &str) -> io::Result<()> {
     colored!(
         buf,
         "{}batch entry {}{}{}{} ... ",
@@ -55,7 +94,7 @@ fn write_entry_header(buf: &mut impl WriteColor, name: &str, expected: &str) ->
 }
 
 pub(crate) fn log_wip_write(
-    buf: &mut impl WriteColor,
+    mut buf: &mut dyn WriteColor,
     wip_path: &Path,
     path: &Path,
     string: &str,
@@ -76,7 +115,7 @@ pub(crate) fn log_wip_write(
 }
 
 pub(crate) fn log_overwrite(
-    buf: &mut impl WriteColor,
+    mut buf: &mut dyn WriteColor,
     path: &Path,
     string: &str,
 ) -> io::Result<()> {
@@ -93,7 +132,12 @@ pub(crate) fn log_overwrite(
     snippet(buf, Yellow, string)
 }
 
-pub(crate) fn mismatch(log: &mut impl WriteColor, expected: &str, actual: &str) -> io::Result<()> {
+pub(crate) fn mismatch(
+    mut log: &mut dyn WriteColor,
+    expected: &str,
+    actual: &str,
+    view: MismatchView,
+) -> io::Result<()> {
     colored!(
         log,
         "{}{}mismatch{}\n\n",
@@ -101,12 +145,131 @@ pub(crate) fn mismatch(log: &mut impl WriteColor, expected: &str, actual: &str)
         fg!(Some(Red)),
         reset!()
     )?;
-    log_snapshot(log, Blue, "EXPECTED", expected.as_bytes())?;
-    log_snapshot(log, Red, "ACTUAL", actual.as_bytes())?;
-    Ok(())
+    match view {
+        MismatchView::Diff => log_diff(log, expected, actual),
+        MismatchView::Full => {
+            log_snapshot(log, Blue, "EXPECTED", expected.as_bytes())?;
+            log_snapshot(log, Red, "ACTUAL", actual.as_bytes())
+        }
+    }
+}
+
+fn log_diff(mut log: &mut dyn WriteColor, expected: &str, actual: &str) -> io::Result<()> {
+    let dotted_line = "┈".repeat(60);
+    let hunks = diff::hunks(&normalize::trim(expected), &normalize::trim(actual));
+
+    colored!(
+        log,
+        "{}{}DIFF{} ({}-expected{}, {}+actual{}):\n{}\n",
+        reset!(),
+        bold!(true),
+        reset!(),
+        fg!(Some(Blue)),
+        reset!(),
+        fg!(Some(Red)),
+        reset!(),
+        dotted_line
+    )?;
+    for hunk in hunks {
+        colored!(
+            log,
+            "{}@@ -{},{} +{},{} @@{}\n",
+            fg!(Some(Cyan)),
+            hunk.expected_start,
+            hunk.expected_len,
+            hunk.actual_start,
+            hunk.actual_len,
+            reset!()
+        )?;
+        let mut lines = &hunk.lines[..];
+        while let Some(line) = lines.first() {
+            if let Line::Context(line) = line {
+                colored!(log, "{} {}\n", reset!(), line)?;
+                lines = &lines[1..];
+                continue;
+            }
+            // the block of changes: some removed lines, followed by some added ones
+            let removed = lines
+                .iter()
+                .take_while(|line| matches!(line, Line::Removed(_)))
+                .count();
+            let added = lines[removed..]
+                .iter()
+                .take_while(|line| matches!(line, Line::Added(_)))
+                .count();
+            let (block, rest) = lines.split_at(removed + added);
+            if removed == added {
+                // most probably, these lines were changed one-by-one, so let's show the changed words
+                for (old, new) in block[..removed].iter().zip(&block[removed..]) {
+                    if let (Line::Removed(old), Line::Added(new)) = (old, new) {
+                        log_changed_words(log, old, new)?;
+                    }
+                }
+            } else {
+                for line in block {
+                    match line {
+                        Line::Removed(line) => colored!(log, "{}-{}\n", fg!(Some(Blue)), line)?,
+                        Line::Added(line) => colored!(log, "{}+{}\n", fg!(Some(Red)), line)?,
+                        Line::Context(_) => unreachable!(),
+                    }
+                }
+            }
+            lines = rest;
+        }
+    }
+    colored!(log, "{}{}\n", reset!(), dotted_line)
+}
+
+fn log_changed_words(mut log: &mut dyn WriteColor, old: &str, new: &str) -> io::Result<()> {
+    let ops = diff::diff(&diff::words(old), &diff::words(new));
+
+    colored!(log, "{}{}-", reset!(), fg!(Some(Blue)))?;
+    for op in &ops {
+        match op {
+            Op::Equal(word) => colored!(log, "{}{}", underline!(false), word)?,
+            Op::Delete(word) => colored!(log, "{}{}", underline!(true), word)?,
+            Op::Insert(_) => {}
+        }
+    }
+    colored!(log, "{}\n{}+", reset!(), fg!(Some(Red)))?;
+    for op in &ops {
+        match op {
+            Op::Equal(word) => colored!(log, "{}{}", underline!(false), word)?,
+            Op::Insert(word) => colored!(log, "{}{}", underline!(true), word)?,
+            Op::Delete(_) => {}
+        }
+    }
+    colored!(log, "{}\n", reset!())
+}
+
+pub(crate) fn timeout(mut log: &mut dyn WriteColor, timeout: Duration) -> io::Result<()> {
+    colored!(
+        log,
+        "{}{}timeout{}\n\nNOTE: the entry was killed after running for {:?}.\n",
+        bold!(true),
+        fg!(Some(Red)),
+        reset!(),
+        timeout
+    )
 }
 
-pub(crate) fn build_status_mismatch(log: &mut impl WriteColor) -> io::Result<()> {
+pub(crate) fn exit_code_mismatch(
+    mut log: &mut dyn WriteColor,
+    expected: i32,
+    actual: &Status,
+) -> io::Result<()> {
+    colored!(
+        log,
+        "{}{}exit code mismatch{}\n\nNOTE: the entry was expected to exit with code {}, but it has finished with {}.\n",
+        bold!(true),
+        fg!(Some(Red)),
+        reset!(),
+        expected,
+        actual
+    )
+}
+
+pub(crate) fn build_status_mismatch(mut log: &mut dyn WriteColor) -> io::Result<()> {
     colored!(
         log,
         "{}{}{}error: {}",
@@ -117,7 +280,7 @@ pub(crate) fn build_status_mismatch(log: &mut impl WriteColor) -> io::Result<()>
     )
 }
 
-pub(crate) fn unexpected_build_success(log: &mut impl WriteColor) -> io::Result<()> {
+pub(crate) fn unexpected_build_success(mut log: &mut dyn WriteColor) -> io::Result<()> {
     build_status_mismatch(log)?;
     colored!(
         log,
@@ -126,14 +289,53 @@ pub(crate) fn unexpected_build_success(log: &mut impl WriteColor) -> io::Result<
     )
 }
 
-pub(crate) fn unexpected_build_error(log: &mut impl WriteColor, error: &[u8]) -> io::Result<()> {
+pub(crate) fn unexpected_build_error(mut log: &mut dyn WriteColor, error: &str) -> io::Result<()> {
     build_status_mismatch(log)?;
     colored!(log, "Entry failed to build; compiler output:{}\n", reset!())?;
     snippet(log, Red, &normalize::trim(error))
 }
 
+pub(crate) fn unexpected_run_failure(
+    mut log: &mut dyn WriteColor,
+    status: &Status,
+    stderr_tail: &str,
+) -> io::Result<()> {
+    build_status_mismatch(log)?;
+    colored!(log, "Entry has finished with {}", status)?;
+    if stderr_tail.is_empty() {
+        colored!(log, ".{}\n", reset!())
+    } else {
+        colored!(log, "; end of its stderr:{}\n", reset!())?;
+        snippet(log, Red, &normalize::trim(stderr_tail))
+    }
+}
+
+pub(crate) fn unexpected_run_success(mut log: &mut dyn WriteColor) -> io::Result<()> {
+    build_status_mismatch(log)?;
+    colored!(
+        log,
+        "Expected test case to fail at runtime, but it exited successfully.{}\n",
+        reset!()
+    )
+}
+
+pub(crate) fn panic_mismatch(
+    mut log: &mut dyn WriteColor,
+    expected: &str,
+    stderr_tail: &str,
+) -> io::Result<()> {
+    build_status_mismatch(log)?;
+    colored!(
+        log,
+        "Expected test case to panic with the message containing {:?}; end of its stderr:{}\n",
+        expected,
+        reset!()
+    )?;
+    snippet(log, Red, &normalize::trim(stderr_tail))
+}
+
 pub(crate) fn log_snapshot(
-    log: &mut impl WriteColor,
+    mut log: &mut dyn WriteColor,
     color: Color,
     header: &str,
     snapshot: &[u8],
@@ -145,7 +347,7 @@ pub(crate) fn log_snapshot(
     Ok(())
 }
 
-fn snippet(log: &mut impl WriteColor, color: Color, content: &str) -> io::Result<()> {
+fn snippet(mut log: &mut dyn WriteColor, color: Color, content: &str) -> io::Result<()> {
     let dotted_line = "┈".repeat(60);
 
     colored!(log, "\n{}{}{}\n", reset!(), fg!(Some(color)), dotted_line)?;
@@ -158,3 +360,73 @@ fn snippet(log: &mut impl WriteColor, color: Color, content: &str) -> io::Result
 
     colored!(log, "{}{}{}\n", fg!(Some(color)), dotted_line, reset!())
 }
+
+pub(crate) fn summary(mut log: &mut dyn WriteColor, summary: &Summary) -> io::Result<()> {
+    if summary.total() == 0 {
+        return Ok(());
+    }
+    let color = if summary.failed() == 0 { Green } else { Red };
+
+    colored!(
+        log,
+        "\n{}{}summary{}: {} entries, {}{} passed{}, {}{} failed{}",
+        reset!(),
+        bold!(true),
+        reset!(),
+        summary.total(),
+        fg!(Some(Green)),
+        summary.passed(),
+        reset!(),
+        fg!(Some(color)),
+        summary.failed(),
+        reset!()
+    )?;
+    if !summary.failures().is_empty() {
+        let failures: Vec<_> = summary
+            .failures()
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        colored!(log, " ({})", failures.join(", "))?;
+    }
+    if summary.ignored() > 0 {
+        colored!(
+            log,
+            ", {}{} ignored{}",
+            fg!(Some(Yellow)),
+            summary.ignored(),
+            reset!()
+        )?;
+    }
+    if summary.skipped() > 0 {
+        colored!(log, ", {} skipped", summary.skipped())?;
+    }
+    if summary.wip() > 0 {
+        colored!(
+            log,
+            ", {}{} written to WIP{}",
+            fg!(Some(Yellow)),
+            summary.wip(),
+            reset!()
+        )?;
+    }
+    colored!(log, "; total time {:.2?}", summary.duration())?;
+    if let Some((name, duration)) = summary.slowest() {
+        colored!(log, ", slowest {} ({:.2?})", name, duration)?;
+    }
+    colored!(log, "\n")?;
+
+    if summary.failed() > 0 {
+        colored!(
+            log,
+            "\n{}{}failed entries:{}\n",
+            bold!(true),
+            fg!(Some(Red)),
+            reset!()
+        )?;
+        for name in summary.failing() {
+            colored!(log, "    {}\n", name)?;
+        }
+    }
+    Ok(())
+}
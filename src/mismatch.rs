@@ -1,36 +1,129 @@
 use itertools::{EitherOrBoth, Itertools};
 use serde::{Deserialize, Serialize};
-use std::{convert::TryFrom, error::Error, process::Output};
+use std::{collections::BTreeMap, fmt, process::Output};
 
+use crate::diff::{self, Hunk};
+use crate::normalize;
+use crate::status::Status;
+
+/// Output of the executed entry, as stored in the snapshot.
 #[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
 pub struct LocalOutput {
-    status: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stdin: Option<Input>,
+    status: Status,
     stdout: Vec<String>,
     stderr: Vec<String>,
 }
 impl LocalOutput {
+    /// Record the way the process was invoked, so that it is stored in snapshot.
+    pub(crate) fn with_input(
+        self,
+        args: &[String],
+        env: &BTreeMap<String, String>,
+        stdin: Option<&[u8]>,
+    ) -> Self {
+        Self {
+            args: args.to_vec(),
+            env: env.clone(),
+            stdin: stdin.map(Input::from),
+            ..self
+        }
+    }
+
     // This is an *extremely* hacky thing.
     // In fact, I'm ignoring every backslash in the output by replacing them with forward slashes,
     // so that the paths, if the program writes them (either correctly or during panic) are
     // compared independently of the platform separator.
     // I'm not really sure if this is a way to go, but...
-    pub fn matches(&self, other: &LocalOutput) -> bool {
-        self.status == other.status
+    pub(crate) fn matches(&self, other: &LocalOutput) -> bool {
+        self.args == other.args
+            && self.env == other.env
+            && self.stdin == other.stdin
+            && self.status == other.status
             && match_lines_with_backslashes(&self.stdout, &other.stdout)
             && match_lines_with_backslashes(&self.stderr, &other.stderr)
     }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+    pub fn env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+    pub fn stdin(&self) -> Option<&Input> {
+        self.stdin.as_ref()
+    }
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+    pub fn stdout(&self) -> &[String] {
+        &self.stdout
+    }
+    pub fn stderr(&self) -> &[String] {
+        &self.stderr
+    }
 }
-impl TryFrom<Output> for LocalOutput {
-    // TODO make some real error and propagate it
-    type Error = Box<dyn Error>;
-    fn try_from(input: Output) -> Result<Self, Box<dyn Error>> {
-        Ok(Self {
-            status: input.status.code().ok_or("No status code")?,
+impl From<Output> for LocalOutput {
+    fn from(input: Output) -> Self {
+        Self {
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            stdin: None,
+            status: input.status.into(),
             stdout: bytes_to_lines(&input.stdout),
             stderr: bytes_to_lines(&input.stderr),
-        })
+        }
+    }
+}
+
+/// Input fed to the entry, stored exactly as it was provided.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Input {
+    /// Input which is valid UTF-8.
+    Text(String),
+    /// Any other input.
+    Bytes(Vec<u8>),
+}
+
+impl Input {
+    /// The input as it was fed to the entry.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Input::Text(text) => text.as_bytes(),
+            Input::Bytes(bytes) => bytes,
+        }
+    }
+
+    /// Lines of the input with the line endings kept, escaped to be shown in the diff.
+    fn escaped_lines(&self) -> Vec<String> {
+        match self {
+            Input::Text(text) => text
+                .split_inclusive('\n')
+                .map(|line| line.escape_debug().to_string())
+                .collect(),
+            Input::Bytes(bytes) => bytes
+                .split_inclusive(|&byte| byte == b'\n')
+                .map(|line| line.escape_ascii().to_string())
+                .collect(),
+        }
     }
 }
+
+impl From<&[u8]> for Input {
+    fn from(input: &[u8]) -> Self {
+        match std::str::from_utf8(input) {
+            Ok(text) => Input::Text(text.to_owned()),
+            Err(_) => Input::Bytes(input.to_vec()),
+        }
+    }
+}
+
 fn bytes_to_lines(input: &[u8]) -> Vec<String> {
     String::from_utf8_lossy(input)
         .to_string()
@@ -62,14 +155,87 @@ where
     actual: T,
 }
 
+/// Difference between the expected and actual compiler output.
 #[derive(Debug)]
 pub struct CompileFailMismatch(SingleMismatch);
+/// Difference between the expected and actual output of the executed entry.
 #[derive(Debug)]
-pub struct RunMismatch(SingleMismatch<LocalOutput>);
+pub struct RunMismatch(Box<SingleMismatch<LocalOutput>>);
+
+/// Differing parts of the run outputs, split by the stream they came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunDiff {
+    pub args: Vec<Hunk>,
+    /// Environment variables, as `NAME=value` lines.
+    pub env: Vec<Hunk>,
+    /// Input lines, with the line endings and non-printable characters escaped.
+    pub stdin: Vec<Hunk>,
+    pub stdout: Vec<Hunk>,
+    pub stderr: Vec<Hunk>,
+    /// Expected and actual status, if they are different.
+    pub status: Option<(Status, Status)>,
+}
+
+impl fmt::Display for RunDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((expected, actual)) = &self.status {
+            writeln!(f, "status: expected {}, got {}", expected, actual)?;
+        }
+        let streams = [
+            ("args", &self.args),
+            ("env", &self.env),
+            ("stdin", &self.stdin),
+            ("stdout", &self.stdout),
+            ("stderr", &self.stderr),
+        ];
+        for (name, hunks) in streams {
+            if !hunks.is_empty() {
+                writeln!(f, "{}:", name)?;
+                hunks.iter().try_for_each(|hunk| write!(f, "{}", hunk))?;
+            }
+        }
+        Ok(())
+    }
+}
 
 impl RunMismatch {
     pub fn new(expected: LocalOutput, actual: LocalOutput) -> Self {
-        RunMismatch(SingleMismatch { expected, actual })
+        RunMismatch(Box::new(SingleMismatch { expected, actual }))
+    }
+    pub fn expected(&self) -> &LocalOutput {
+        &self.0.expected
+    }
+    pub fn actual(&self) -> &LocalOutput {
+        &self.0.actual
+    }
+    pub fn diff(&self) -> RunDiff {
+        let SingleMismatch { expected, actual } = &*self.0;
+        let env = |output: &LocalOutput| -> Vec<String> {
+            output
+                .env
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect()
+        };
+        let stdin = |output: &LocalOutput| -> Vec<String> {
+            output
+                .stdin
+                .as_ref()
+                .map(Input::escaped_lines)
+                .unwrap_or_default()
+        };
+        RunDiff {
+            args: exact_hunks(&expected.args, &actual.args),
+            env: exact_hunks(&env(expected), &env(actual)),
+            stdin: exact_hunks(&stdin(expected), &stdin(actual)),
+            stdout: stream_hunks(&expected.stdout, &actual.stdout),
+            stderr: stream_hunks(&expected.stderr, &actual.stderr),
+            status: if expected.status == actual.status {
+                None
+            } else {
+                Some((expected.status.clone(), actual.status.clone()))
+            },
+        }
     }
 }
 
@@ -80,4 +246,29 @@ impl CompileFailMismatch {
             actual: actual.into(),
         })
     }
+    pub fn expected(&self) -> &str {
+        &self.0.expected
+    }
+    pub fn actual(&self) -> &str {
+        &self.0.actual
+    }
+    pub fn hunks(&self) -> Vec<Hunk> {
+        diff::hunks(
+            &normalize::trim(&self.0.expected),
+            &normalize::trim(&self.0.actual),
+        )
+    }
+}
+
+fn exact_hunks(expected: &[String], actual: &[String]) -> Vec<Hunk> {
+    let expected: Vec<_> = expected.iter().map(String::as_str).collect();
+    let actual: Vec<_> = actual.iter().map(String::as_str).collect();
+    diff::line_hunks(&expected, &actual)
+}
+
+fn stream_hunks(expected: &[String], actual: &[String]) -> Vec<Hunk> {
+    if match_lines_with_backslashes(expected, actual) {
+        return Vec::new();
+    }
+    exact_hunks(expected, actual)
 }
@@ -72,7 +72,10 @@ fn filter_map(line: &str) -> Option<String> {
         ];
     };
     // stripping out final compilation lines
-    if CUT_OUT.iter().any(|prefix| line.trim().starts_with(prefix)) {
+    let trimmed = line.trim();
+    let warnings_summary = trimmed.starts_with("warning: ")
+        && (trimmed.ends_with(" warning emitted") || trimmed.ends_with(" warnings emitted"));
+    if warnings_summary || CUT_OUT.iter().any(|prefix| trimmed.starts_with(prefix)) {
         None
     } else {
         Some(line.to_owned())
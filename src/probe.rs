@@ -0,0 +1,279 @@
+//! Discovering the arguments for compiling the entries from `cargo metadata`
+//! and the JSON messages of `cargo build`, without touching the source tree.
+
+use crate::cargo_rustc;
+use crate::config::BuildConfig;
+use crate::result::{error::BatchError, BatchResult};
+use crate::rustc_args;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    env::{var, var_os},
+    path::{Path, PathBuf},
+};
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    resolve: Resolve,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: String,
+    edition: String,
+    manifest_path: PathBuf,
+    targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Resolve {
+    nodes: Vec<Node>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    id: String,
+    deps: Vec<NodeDep>,
+}
+
+#[derive(Deserialize)]
+struct NodeDep {
+    /// Name of the dependency as seen from the code, i.e. after the renaming.
+    name: String,
+    pkg: String,
+    dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Deserialize)]
+struct DepKind {
+    /// `None` for the normal dependencies, `dev` or `build` for the others.
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum Message {
+    CompilerArtifact {
+        package_id: String,
+        target: Target,
+        filenames: Vec<PathBuf>,
+        features: Vec<String>,
+    },
+    BuildScriptExecuted {
+        package_id: String,
+        linked_libs: Vec<String>,
+        linked_paths: Vec<String>,
+        cfgs: Vec<String>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Library which can be passed to `--extern`.
+struct Library {
+    crate_name: String,
+    path: PathBuf,
+}
+
+fn is_library(target: &Target) -> bool {
+    target
+        .kind
+        .iter()
+        .any(|kind| kind.ends_with("lib") || kind == "proc-macro")
+}
+
+/// The file to be linked: the rlib if there's one, or the dynamic library for proc-macros.
+fn library_file(filenames: &[PathBuf]) -> Option<&PathBuf> {
+    fn extension(path: &Path) -> Option<&str> {
+        path.extension().and_then(|ext| ext.to_str())
+    }
+    filenames
+        .iter()
+        .find(|path| extension(path) == Some("rlib"))
+        .or_else(|| {
+            filenames
+                .iter()
+                .find(|path| matches!(extension(path), Some("so" | "dylib" | "dll")))
+        })
+}
+
+/// Directory with the dependencies of the crate itself, i.e. the one for the target platform.
+///
+/// The libraries are reported from there, but the binaries are reported after being copied
+/// into its parent directory.
+fn output_dir(target: &Target, filenames: &[PathBuf]) -> Option<PathBuf> {
+    if is_library(target) {
+        filenames
+            .iter()
+            .filter_map(|path| path.parent())
+            .find(|dir| dir.file_name().map_or(false, |name| name == "deps"))
+            .map(Path::to_owned)
+    } else if target.kind.iter().any(|kind| kind == "bin") {
+        filenames
+            .first()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join("deps"))
+    } else {
+        None
+    }
+}
+
+/// The `--cfg` values from `RUSTFLAGS`, which cargo passes to the crate itself.
+fn rustflags_cfgs() -> BatchResult<Vec<String>> {
+    let rustflags = match var("RUSTFLAGS") {
+        Ok(rustflags) => rustflags,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut words = rustc_args::split(&rustflags)
+        .map_err(|err| BatchError::Probe(format!("malformed RUSTFLAGS: {}", err)))?
+        .into_iter();
+    let mut cfgs = Vec::new();
+    while let Some(word) = words.next() {
+        if word == "--cfg" {
+            cfgs.extend(words.next());
+        } else if let Some(cfg) = word.strip_prefix("--cfg=") {
+            cfgs.push(cfg.to_owned());
+        }
+    }
+    Ok(cfgs)
+}
+
+pub fn builder_args(build: &BuildConfig) -> BatchResult<Vec<String>> {
+    let metadata: Metadata = serde_json::from_slice(&cargo_rustc::metadata(build)?)
+        .map_err(|err| BatchError::Probe(format!("unexpected cargo metadata: {}", err)))?;
+
+    let manifest_path = Path::new(&var_os("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.toml");
+    let package = metadata
+        .packages
+        .iter()
+        .find(|package| package.manifest_path == manifest_path)
+        .ok_or_else(|| BatchError::Probe(format!("no package at {}", manifest_path.display())))?;
+    let lib = package.targets.iter().find(|target| is_library(target));
+    let node = metadata
+        .resolve
+        .nodes
+        .iter()
+        .find(|node| node.id == package.id)
+        .ok_or_else(|| BatchError::Probe(format!("package {} is not resolved", package.id)))?;
+    // the crate itself is available to the entries, as well as its normal dependencies
+    let mut externs: HashMap<&str, String> = node
+        .deps
+        .iter()
+        .filter(|dep| dep.dep_kinds.iter().any(|kind| kind.kind.is_none()))
+        .map(|dep| (dep.pkg.as_str(), dep.name.clone()))
+        .collect();
+    if let Some(lib) = lib {
+        externs.insert(&package.id, lib.name.replace('-', "_"));
+    }
+
+    let messages =
+        cargo_rustc::build_messages(build, if lib.is_some() { "--lib" } else { "--bins" })?;
+    let mut candidates: Vec<(String, PathBuf)> = Vec::new();
+    let mut output = None;
+    let mut features = Vec::new();
+    let mut cfgs = Vec::new();
+    let mut linked_libs = Vec::new();
+    let mut linked_paths = BTreeSet::new();
+    let mut dependency_dirs = BTreeSet::new();
+    for line in messages.split(|&byte| byte == b'\n') {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let message = serde_json::from_slice(line)
+            .map_err(|err| BatchError::Probe(format!("unexpected cargo message: {}", err)))?;
+        match message {
+            Message::CompilerArtifact {
+                package_id,
+                target,
+                filenames,
+                features: artifact_features,
+            } => {
+                if package_id == package.id {
+                    features = artifact_features;
+                    output = output.or_else(|| output_dir(&target, &filenames));
+                }
+                if !is_library(&target) {
+                    continue;
+                }
+                // the transitive dependencies can be in another directory (e.g. the proc-macros
+                // built for the host while cross-compiling), so all of them are searched
+                if let Some(dir) = library_file(&filenames).and_then(|path| path.parent()) {
+                    dependency_dirs.insert(dir.to_owned());
+                }
+                if let Some(path) = library_file(&filenames) {
+                    if externs.contains_key(package_id.as_str()) {
+                        candidates.push((package_id, path.clone()));
+                    }
+                }
+            }
+            Message::BuildScriptExecuted {
+                package_id,
+                linked_libs: libs,
+                linked_paths: paths,
+                cfgs: script_cfgs,
+            } => {
+                if package_id == package.id {
+                    cfgs = script_cfgs;
+                    linked_libs = libs;
+                }
+                linked_paths.extend(paths);
+            }
+            Message::Other => {}
+        }
+    }
+
+    // the dependencies for other platforms are not built at all, so they are skipped;
+    // the library built twice (e.g. for the host as a build dependency too, or with another
+    // set of features) is taken from the directory the crate itself was built in
+    let mut libraries = Vec::new();
+    for (package_id, path) in &candidates {
+        let is_preferred = |path: &PathBuf| path.parent() == output.as_deref();
+        let preferred = candidates
+            .iter()
+            .any(|(other_id, other)| other_id == package_id && is_preferred(other));
+        if preferred && !is_preferred(path) {
+            continue;
+        }
+        if let Some(crate_name) = externs.remove(package_id.as_str()) {
+            libraries.push(Library {
+                crate_name,
+                path: path.clone(),
+            });
+        }
+    }
+
+    let mut args = Vec::new();
+    for dir in dependency_dirs {
+        args.push("-L".to_owned());
+        args.push(format!("dependency={}", dir.display()));
+    }
+    for path in linked_paths {
+        args.push("-L".to_owned());
+        args.push(path);
+    }
+    for lib in linked_libs {
+        args.push("-l".to_owned());
+        args.push(lib);
+    }
+    for feature in features {
+        args.push("--cfg".to_owned());
+        args.push(format!("feature=\"{}\"", feature));
+    }
+    for cfg in cfgs.into_iter().chain(rustflags_cfgs()?) {
+        args.push("--cfg".to_owned());
+        args.push(cfg);
+    }
+    for library in &libraries {
+        args.push("--extern".to_owned());
+        args.push(format!("{}={}", library.crate_name, library.path.display()));
+    }
+    args.push(format!("--edition={}", package.edition));
+    Ok(args)
+}
@@ -0,0 +1,244 @@
+//! Reporting the progress and results of the batch.
+//!
+//! Every callback of the [`Reporter`] receives the log of the current entry, i.e. the writer
+//! built by `Config`'s writer builder, so that the output of parallel entries is kept separate.
+//! Callbacks do nothing by default, so that the custom reporters can implement only the ones
+//! they are interested in.
+
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use termcolor::WriteColor;
+
+use crate::config::MismatchView;
+use crate::entry::Expected;
+use crate::logging;
+use crate::result::{EntryResult, Status, Summary};
+
+#[allow(unused_variables)]
+pub trait Reporter: Send + Sync {
+    /// The batch is about to start, with the provided number of entries.
+    fn batch_started(&self, entries: usize) -> io::Result<()> {
+        Ok(())
+    }
+    /// The batch is finished; `log` is kept in the result after the logs of all entries.
+    fn batch_finished(&self, log: &mut dyn WriteColor, summary: &Summary) -> io::Result<()> {
+        Ok(())
+    }
+    /// There are no entries in the batch.
+    fn no_entries(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The entry is about to be built.
+    fn entry_started(
+        &self,
+        log: &mut dyn WriteColor,
+        path: &Path,
+        expected: Expected,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry couldn't be started at all, e.g. its glob pattern is incorrect.
+    fn entry_fail_to_start(&self, log: &mut dyn WriteColor, path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry was filtered out, so it won't be run.
+    fn entry_skipped(&self, log: &mut dyn WriteColor, path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry is marked as ignored, so it won't be run.
+    fn entry_ignored(
+        &self,
+        log: &mut dyn WriteColor,
+        path: &Path,
+        expected: Expected,
+        reason: &str,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry is finished, either successfully or not.
+    ///
+    /// This is called after every other callback for this entry.
+    fn entry_finished(
+        &self,
+        log: &mut dyn WriteColor,
+        path: &Path,
+        res: &EntryResult,
+        duration: Duration,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry has passed.
+    fn ok(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The entry should have failed to compile, but it was built successfully.
+    fn unexpected_build_success(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry should have compiled, but it has failed; `stderr` is the compiler output.
+    fn unexpected_build_error(&self, log: &mut dyn WriteColor, stderr: &str) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry should have run successfully, but it has failed.
+    fn unexpected_run_failure(
+        &self,
+        log: &mut dyn WriteColor,
+        status: &Status,
+        stderr_tail: &str,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry should have failed at runtime, but it has finished successfully.
+    fn unexpected_run_success(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry has failed at runtime, but not with the expected panic.
+    fn panic_mismatch(
+        &self,
+        log: &mut dyn WriteColor,
+        expected: &str,
+        stderr_tail: &str,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry was killed after running for too long.
+    fn timeout(&self, log: &mut dyn WriteColor, timeout: Duration) -> io::Result<()> {
+        Ok(())
+    }
+    /// The entry has finished with the unexpected exit code.
+    fn exit_code_mismatch(
+        &self,
+        log: &mut dyn WriteColor,
+        expected: i32,
+        actual: &Status,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The output of the entry doesn't match the snapshot.
+    fn mismatch(
+        &self,
+        log: &mut dyn WriteColor,
+        expected: &str,
+        actual: &str,
+        view: MismatchView,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// There's no snapshot for the entry, and its output is written to the WIP folder.
+    fn wip_write(
+        &self,
+        log: &mut dyn WriteColor,
+        wip_path: &Path,
+        path: &Path,
+        content: &str,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The output of the entry is written directly to the snapshot.
+    fn overwrite(&self, log: &mut dyn WriteColor, path: &Path, content: &str) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Default reporter, writing the colored human-readable output.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ColoredReporter;
+
+impl Reporter for ColoredReporter {
+    fn batch_started(&self, _: usize) -> io::Result<()> {
+        logging::batch_started()
+    }
+    fn no_entries(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        logging::no_entries(log)
+    }
+    fn batch_finished(&self, log: &mut dyn WriteColor, summary: &Summary) -> io::Result<()> {
+        logging::summary(log, summary)
+    }
+    fn entry_started(
+        &self,
+        log: &mut dyn WriteColor,
+        path: &Path,
+        expected: Expected,
+    ) -> io::Result<()> {
+        logging::log_entry_start(log, path, expected)
+    }
+    fn entry_fail_to_start(&self, log: &mut dyn WriteColor, path: &Path) -> io::Result<()> {
+        logging::log_entry_fail_to_start(log, path)
+    }
+    fn entry_skipped(&self, log: &mut dyn WriteColor, path: &Path) -> io::Result<()> {
+        logging::skipped(log, path)
+    }
+    fn entry_ignored(
+        &self,
+        log: &mut dyn WriteColor,
+        path: &Path,
+        expected: Expected,
+        reason: &str,
+    ) -> io::Result<()> {
+        logging::log_entry_start(log, path, expected)?;
+        logging::ignored(log, reason)
+    }
+    fn ok(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        logging::ok(log)
+    }
+    fn unexpected_build_success(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        logging::unexpected_build_success(log)
+    }
+    fn unexpected_build_error(&self, log: &mut dyn WriteColor, stderr: &str) -> io::Result<()> {
+        logging::unexpected_build_error(log, stderr)
+    }
+    fn unexpected_run_failure(
+        &self,
+        log: &mut dyn WriteColor,
+        status: &Status,
+        stderr_tail: &str,
+    ) -> io::Result<()> {
+        logging::unexpected_run_failure(log, status, stderr_tail)
+    }
+    fn unexpected_run_success(&self, log: &mut dyn WriteColor) -> io::Result<()> {
+        logging::unexpected_run_success(log)
+    }
+    fn panic_mismatch(
+        &self,
+        log: &mut dyn WriteColor,
+        expected: &str,
+        stderr_tail: &str,
+    ) -> io::Result<()> {
+        logging::panic_mismatch(log, expected, stderr_tail)
+    }
+    fn timeout(&self, log: &mut dyn WriteColor, timeout: Duration) -> io::Result<()> {
+        logging::timeout(log, timeout)
+    }
+    fn exit_code_mismatch(
+        &self,
+        log: &mut dyn WriteColor,
+        expected: i32,
+        actual: &Status,
+    ) -> io::Result<()> {
+        logging::exit_code_mismatch(log, expected, actual)
+    }
+    fn mismatch(
+        &self,
+        log: &mut dyn WriteColor,
+        expected: &str,
+        actual: &str,
+        view: MismatchView,
+    ) -> io::Result<()> {
+        logging::mismatch(log, expected, actual, view)
+    }
+    fn wip_write(
+        &self,
+        log: &mut dyn WriteColor,
+        wip_path: &Path,
+        path: &Path,
+        content: &str,
+    ) -> io::Result<()> {
+        logging::log_wip_write(log, wip_path, path, content)
+    }
+    fn overwrite(&self, log: &mut dyn WriteColor, path: &Path, content: &str) -> io::Result<()> {
+        logging::log_overwrite(log, path, content)
+    }
+}
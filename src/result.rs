@@ -1,22 +1,53 @@
 use crate::term;
 use glob::{GlobError, PatternError};
 use std::io;
+use std::time::Duration;
 use termcolor::{Buffer, StandardStream, WriteColor};
 
 pub mod error;
 use error::*;
+mod junit;
+mod summary;
+
+pub use summary::Summary;
+
+pub use crate::diff::{Hunk, Line};
+pub use crate::mismatch::{CompileFailMismatch, Input, LocalOutput, RunDiff, RunMismatch};
+pub use crate::status::Status;
 
 pub enum BatchRunResult<W: WriteColor = StandardStream> {
     NoEntries(Option<W>),
-    ResultsMap(Vec<(String, EntryOutput<W>)>),
+    ResultsMap {
+        /// Results of every entry, in the order the entries were added.
+        outputs: Vec<(String, EntryOutput<W>)>,
+        /// Log of the batch summary.
+        log: Option<W>,
+        /// Wall-clock time of the whole batch.
+        duration: Duration,
+    },
 }
 pub type BatchResult<T = BatchRunResult> = std::result::Result<T, BatchError>;
 
 impl<W: WriteColor> BatchRunResult<W> {
+    /// Results of every entry, in the order the entries were added.
+    pub fn outputs(&self) -> &[(String, EntryOutput<W>)] {
+        match self {
+            BatchRunResult::NoEntries(_) => &[],
+            BatchRunResult::ResultsMap { outputs, .. } => outputs,
+        }
+    }
+    /// Wall-clock time of the whole batch.
+    pub fn duration(&self) -> Duration {
+        match self {
+            BatchRunResult::NoEntries(_) => Duration::ZERO,
+            BatchRunResult::ResultsMap { duration, .. } => *duration,
+        }
+    }
     pub fn errors(&self) -> Option<Vec<(&String, &EntryFailed)>> {
-        if let BatchRunResult::ResultsMap(map) = self {
+        if let BatchRunResult::ResultsMap { outputs, .. } = self {
             Some(
-                map.iter()
+                outputs
+                    .iter()
                     .filter_map(|(file, res)| res.err().map(|err| (file, err)))
                     .collect(),
             )
@@ -47,10 +78,13 @@ impl BatchRunResult<Buffer> {
     pub fn print_all(&mut self) -> std::result::Result<(), PrintError> {
         match self {
             BatchRunResult::NoEntries(buf) => term::print(buf.take()),
-            BatchRunResult::ResultsMap(map) => map
-                .iter_mut()
-                .map(|(_, out)| out)
-                .try_for_each(EntryOutput::print),
+            BatchRunResult::ResultsMap { outputs, log, .. } => {
+                outputs
+                    .iter_mut()
+                    .map(|(_, out)| out)
+                    .try_for_each(EntryOutput::print)?;
+                term::print(log.take())
+            }
         }
     }
 }
@@ -59,20 +93,64 @@ pub type EntryResult<T = ()> = std::result::Result<T, EntryFailed>;
 pub struct EntryOutput<W: WriteColor> {
     res: EntryResult,
     buf: Option<W>,
+    duration: Duration,
+    skipped: bool,
+    ignored: Option<String>,
 }
 impl<W: WriteColor> EntryOutput<W> {
-    pub(crate) fn new(res: EntryResult, buf: W) -> Self {
+    pub(crate) fn new(res: EntryResult, buf: W, duration: Duration) -> Self {
         Self {
             res,
             buf: Some(buf),
+            duration,
+            skipped: false,
+            ignored: None,
+        }
+    }
+    pub(crate) fn skipped(buf: W) -> Self {
+        Self {
+            res: Ok(()),
+            buf: Some(buf),
+            duration: Duration::ZERO,
+            skipped: true,
+            ignored: None,
+        }
+    }
+    pub(crate) fn ignored(buf: W, reason: String) -> Self {
+        Self {
+            res: Ok(()),
+            buf: Some(buf),
+            duration: Duration::ZERO,
+            skipped: false,
+            ignored: Some(reason),
         }
     }
     pub fn is_ok(&self) -> bool {
         self.res.is_ok()
     }
+    /// Whether the entry was filtered out and not run at all.
+    pub fn is_skipped(&self) -> bool {
+        self.skipped
+    }
+    /// Whether the entry was marked as ignored and not run at all.
+    pub fn is_ignored(&self) -> bool {
+        self.ignored.is_some()
+    }
+    /// The reason the entry was ignored for, which may be empty if none was provided.
+    pub fn ignore_reason(&self) -> Option<&str> {
+        self.ignored.as_deref()
+    }
     pub fn err(&self) -> Option<&EntryFailed> {
         self.res.as_ref().err()
     }
+    /// Log of the entry, unless it was already printed.
+    pub fn log(&self) -> Option<&W> {
+        self.buf.as_ref()
+    }
+    /// Time spent on the entry, including its compilation.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
 }
 impl EntryOutput<Buffer> {
     pub fn print(&mut self) -> std::result::Result<(), PrintError> {
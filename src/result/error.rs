@@ -1,8 +1,10 @@
 use crate::mismatch::{CompileFailMismatch, RunMismatch};
+use crate::status::Status;
 use glob::{GlobError, PatternError};
 use std::ffi::OsString;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -15,12 +17,47 @@ pub enum EntryFailed {
     ExpectedNotExist(#[source] NoExpected),
     #[error("Compiler error mismatch")]
     CompileFailMismatch(CompileFailMismatch),
+    #[error("Compiler warnings mismatch")]
+    WarningsMismatch(CompileFailMismatch),
     #[error("Runtime output mismatch")]
     RunMismatch(RunMismatch),
+    #[error("Entry should run successfully, but it has finished with {status}")]
+    RunFailed { status: Status, stderr_tail: String },
+    #[error("Entry should fail at runtime, but it exited successfully")]
+    ShouldFail,
+    #[error("Entry should panic with the message containing {expected:?}, but it did not")]
+    PanicMismatch {
+        expected: String,
+        stderr_tail: String,
+    },
+    #[error("Entry has finished with {actual}, expected exit code {expected}")]
+    ExitCodeMismatch { expected: i32, actual: Status },
+    #[error("Entry was killed after running for {0:?}")]
+    Timeout(Duration),
     #[error("Internal error")]
     Error(#[source] EntryError),
 }
 
+impl EntryFailed {
+    /// Name of the failure variant, to be used in the machine-readable reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EntryFailed::ShouldCompile(_) => "ShouldCompile",
+            EntryFailed::ShouldNotCompile => "ShouldNotCompile",
+            EntryFailed::ExpectedNotExist(_) => "ExpectedNotExist",
+            EntryFailed::CompileFailMismatch(_) => "CompileFailMismatch",
+            EntryFailed::WarningsMismatch(_) => "WarningsMismatch",
+            EntryFailed::RunMismatch(_) => "RunMismatch",
+            EntryFailed::RunFailed { .. } => "RunFailed",
+            EntryFailed::ShouldFail => "ShouldFail",
+            EntryFailed::PanicMismatch { .. } => "PanicMismatch",
+            EntryFailed::ExitCodeMismatch { .. } => "ExitCodeMismatch",
+            EntryFailed::Timeout(_) => "Timeout",
+            EntryFailed::Error(_) => "Error",
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum NoExpected {
     #[error("Output written to WIP folder")]
@@ -33,6 +70,10 @@ pub enum NoExpected {
 pub enum BatchError {
     #[error("Failed to execute cargo: {0}")]
     Cargo(#[source] io::Error),
+    #[error("Failed to probe the crate build: {0}")]
+    Probe(String),
+    #[error("Unhandled flag in the captured rustc command: {0}")]
+    UnhandledFlag(String),
     #[error("Configuration error: {0}")]
     ConfigError(#[source] ConfigError),
     #[error("General IO error: {0}")]
@@ -43,6 +84,12 @@ pub enum BatchError {
 pub enum ConfigError {
     #[error("Incorrect value of BATCH_RUN environmental variable: expected either \"Overwrite\" or \"Wip\", got {}", .0.to_string_lossy())]
     UpdateEnvVar(OsString),
+    #[error("Incorrect value of BATCH_RUN_JOBS environmental variable: expected a positive number, got {}", .0.to_string_lossy())]
+    JobsEnvVar(OsString),
+    #[error("Incorrect value of BATCH_RUN_TIMEOUT environmental variable: expected a positive number of seconds, got {}", .0.to_string_lossy())]
+    TimeoutEnvVar(OsString),
+    #[error("Incorrect value of BATCH_RUN_FILTER environmental variable: expected a valid UTF-8 string, got {}", .0.to_string_lossy())]
+    FilterEnvVar(OsString),
 }
 
 #[derive(Debug, Error)]
@@ -56,6 +103,8 @@ pub enum EntryError {
     Glob(#[source] GlobError),
     #[error("General IO error: {0}")]
     Io(#[source] io::Error),
+    #[error("Incorrect header directive at line {0}: {1}")]
+    Header(usize, String),
     #[error("Unable to open provided path: {}, error: {}", .0.display(), .1)]
     Open(PathBuf, #[source] io::Error),
     #[error("Incorrect glob pattern: {0}")]
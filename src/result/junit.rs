@@ -0,0 +1,133 @@
+//! JUnit XML report, as understood by the most CI systems.
+
+use super::{error::EntryFailed, error::NoExpected, BatchRunResult};
+use std::io::{self, Write};
+use std::time::Duration;
+use termcolor::WriteColor;
+
+impl<W: WriteColor> BatchRunResult<W> {
+    /// Write the results as the JUnit XML report with a single test suite.
+    ///
+    /// Every entry is reported as a separate test case. Failure type is the name of the
+    /// [`EntryFailed`] variant, and the failure body contains the diff for the mismatches
+    /// or the relevant output for other failures.
+    pub fn write_junit(&self, suite_name: &str, mut out: impl Write) -> io::Result<()> {
+        let entries = self.outputs();
+        let errors = entries
+            .iter()
+            .filter(|(_, output)| matches!(output.err(), Some(EntryFailed::Error(_))))
+            .count();
+        let failures = entries
+            .iter()
+            .filter(|(_, output)| output.err().is_some())
+            .count()
+            - errors;
+        let skipped = entries
+            .iter()
+            .filter(|(_, output)| output.is_skipped() || output.is_ignored())
+            .count();
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}" time="{}">"#,
+            escape(suite_name),
+            entries.len(),
+            failures,
+            errors,
+            skipped,
+            seconds(self.duration())
+        )?;
+        for (name, output) in entries {
+            write!(
+                out,
+                r#"  <testcase name="{}" classname="{}" time="{}""#,
+                escape(name),
+                escape(suite_name),
+                seconds(output.duration())
+            )?;
+            let err = match output.err() {
+                Some(err) => err,
+                None if output.is_skipped() => {
+                    writeln!(out, ">")?;
+                    writeln!(out, r#"    <skipped message="filtered out"/>"#)?;
+                    writeln!(out, "  </testcase>")?;
+                    continue;
+                }
+                None if output.is_ignored() => {
+                    writeln!(out, ">")?;
+                    writeln!(
+                        out,
+                        r#"    <skipped message="{}"/>"#,
+                        escape(output.ignore_reason().unwrap_or_default())
+                    )?;
+                    writeln!(out, "  </testcase>")?;
+                    continue;
+                }
+                None => {
+                    writeln!(out, "/>")?;
+                    continue;
+                }
+            };
+            let tag = match err {
+                EntryFailed::Error(_) => "error",
+                _ => "failure",
+            };
+            writeln!(out, ">")?;
+            writeln!(
+                out,
+                r#"    <{} type="{}" message="{}">{}</{}>"#,
+                tag,
+                err.kind(),
+                escape(&err.to_string()),
+                escape(&details(err)),
+                tag
+            )?;
+            writeln!(out, "  </testcase>")?;
+        }
+        writeln!(out, "</testsuite>")
+    }
+}
+
+fn details(err: &EntryFailed) -> String {
+    match err {
+        EntryFailed::ShouldCompile(stderr) => stderr.clone(),
+        EntryFailed::ExpectedNotExist(NoExpected::ToWip(content))
+        | EntryFailed::ExpectedNotExist(NoExpected::Direct(content)) => content.clone(),
+        EntryFailed::CompileFailMismatch(mismatch) | EntryFailed::WarningsMismatch(mismatch) => {
+            mismatch.hunks().iter().map(ToString::to_string).collect()
+        }
+        EntryFailed::RunMismatch(mismatch) => mismatch.diff().to_string(),
+        EntryFailed::RunFailed { stderr_tail, .. }
+        | EntryFailed::PanicMismatch { stderr_tail, .. } => stderr_tail.clone(),
+        EntryFailed::Error(err) => err.to_string(),
+        EntryFailed::ShouldNotCompile
+        | EntryFailed::ShouldFail
+        | EntryFailed::ExitCodeMismatch { .. }
+        | EntryFailed::Timeout(_) => String::new(),
+    }
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// Escape the string for use both in XML attributes and text,
+/// dropping the control characters which are not allowed in XML at all.
+fn escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' | '\t' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
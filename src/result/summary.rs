@@ -0,0 +1,92 @@
+use super::{error::EntryFailed, error::NoExpected, BatchRunResult};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use termcolor::WriteColor;
+
+/// Overall statistics of the finished batch.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    passed: usize,
+    skipped: usize,
+    ignored: usize,
+    failures: BTreeMap<&'static str, usize>,
+    wip: usize,
+    failing: Vec<String>,
+    duration: Duration,
+    slowest: Option<(String, Duration)>,
+}
+
+impl Summary {
+    pub fn total(&self) -> usize {
+        self.passed + self.failed() + self.skipped + self.ignored
+    }
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+    pub fn failed(&self) -> usize {
+        self.failing.len()
+    }
+    /// Number of entries which were filtered out.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+    /// Number of entries which were marked as ignored.
+    pub fn ignored(&self) -> usize {
+        self.ignored
+    }
+    /// Number of failed entries for every kind of failure, i.e. the name of `EntryFailed` variant.
+    pub fn failures(&self) -> &BTreeMap<&'static str, usize> {
+        &self.failures
+    }
+    /// Number of entries with no snapshot, whose output was written to the WIP folder.
+    pub fn wip(&self) -> usize {
+        self.wip
+    }
+    /// Names of the failed entries, in the order of execution.
+    pub fn failing(&self) -> &[String] {
+        &self.failing
+    }
+    /// Wall-clock time of the whole batch; with several jobs, it is less than
+    /// the total time spent on the entries.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+    /// The entry which took the most time, if there was any.
+    pub fn slowest(&self) -> Option<(&str, Duration)> {
+        self.slowest
+            .as_ref()
+            .map(|(name, duration)| (name.as_str(), *duration))
+    }
+}
+
+impl<W: WriteColor> BatchRunResult<W> {
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary {
+            duration: self.duration(),
+            ..Summary::default()
+        };
+        for (name, output) in self.outputs() {
+            match output.err() {
+                None if output.is_skipped() => summary.skipped += 1,
+                None if output.is_ignored() => summary.ignored += 1,
+                None => summary.passed += 1,
+                Some(err) => {
+                    *summary.failures.entry(err.kind()).or_default() += 1;
+                    if let EntryFailed::ExpectedNotExist(NoExpected::ToWip(_)) = err {
+                        summary.wip += 1;
+                    }
+                    summary.failing.push(name.clone());
+                }
+            }
+            let duration = output.duration();
+            let is_slowest = match &summary.slowest {
+                Some((_, slowest)) => duration > *slowest,
+                None => true,
+            };
+            if is_slowest {
+                summary.slowest = Some((name.clone(), duration));
+            }
+        }
+        summary
+    }
+}
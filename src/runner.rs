@@ -1,14 +1,21 @@
-use crate::binary::BUILDER;
-use crate::config::Config;
-use crate::entry::{expand_globs, Entry};
-use crate::logging;
-use crate::result::{BatchResult, BatchRunResult};
+use crate::binary::BinaryBuilder;
+use crate::config::{BuildConfig, Config};
+use crate::entry::{expand_globs, Entry, ExpandedEntry};
+use crate::result::{BatchResult, BatchRunResult, EntryOutput};
 
+use std::{
+    collections::{hash_map, HashMap},
+    panic,
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
 use termcolor::{StandardStream, WriteColor};
 
 #[derive(Debug, Default)]
 pub struct Runner {
     entries: Vec<Entry>,
+    builders: Builders,
 }
 
 impl Runner {
@@ -16,44 +23,153 @@ impl Runner {
         Self::default()
     }
 
-    pub fn add_entry(&mut self, entry: Entry) {
+    pub fn add_entry(&mut self, entry: Entry) -> usize {
         self.entries.push(entry);
+        self.entries.len() - 1
+    }
+
+    pub fn entry_mut(&mut self, index: usize) -> &mut Entry {
+        &mut self.entries[index]
     }
 
     pub fn run(&mut self) -> BatchResult<BatchRunResult<StandardStream>> {
         let config = Config::from_env()?;
         self.run_with_config(config)
     }
-    pub fn run_with_config<W: WriteColor>(
+    pub fn run_with_config<W: WriteColor + Send>(
         &mut self,
         cfg: Config<W>,
     ) -> BatchResult<BatchRunResult<W>> {
-        let cwd = std::env::current_dir()?;
-        std::env::set_current_dir(
-            std::env::var_os("CARGO_MANIFEST_DIR").expect("Couldn't get manifest dir"),
-        )?;
-        let res = self.run_impl(cfg);
-        std::env::set_current_dir(cwd)?;
-        res
-    }
-    fn run_impl<W: WriteColor>(&mut self, cfg: Config<W>) -> BatchResult<BatchRunResult<W>> {
-        let entries = expand_globs(&self.entries, &cfg.writer());
+        let entries = expand_globs(&self.entries, &cfg);
 
-        let builder = &*BUILDER;
+        self.builders.prepare(&entries, &cfg)?;
+        let builders = &self.builders;
 
-        print!("\n\n");
+        let start = Instant::now();
+        let reporter = cfg.reporter();
+        reporter.batch_started(entries.len())?;
+        if let Some(events) = cfg.events() {
+            events.batch_started(entries.len())?;
+        }
 
-        if entries.is_empty() {
-            let mut log = cfg.writer().build();
-            logging::no_entries(&mut log)?;
-            Ok(BatchRunResult::NoEntries(Some(log)))
+        let mut log = cfg.writer().build();
+        let mut res = if entries.is_empty() {
+            reporter.no_entries(&mut log)?;
+            BatchRunResult::NoEntries(None)
         } else {
-            Ok(BatchRunResult::ResultsMap(
-                entries
-                    .into_iter()
-                    .map(|entry| (entry.path().display().to_string(), entry.run(builder, &cfg)))
-                    .collect(),
-            ))
+            let outputs = run_entries(entries, builders, &cfg);
+            BatchRunResult::ResultsMap {
+                outputs,
+                log: None,
+                duration: start.elapsed(),
+            }
+        };
+
+        let summary = res.summary();
+        reporter.batch_finished(&mut log, &summary)?;
+        if let Some(events) = cfg.events() {
+            events.batch_finished(&summary)?;
+        }
+        match &mut res {
+            BatchRunResult::NoEntries(slot) | BatchRunResult::ResultsMap { log: slot, .. } => {
+                *slot = Some(log)
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Binary builders for every build configuration requested by the entries.
+#[derive(Debug, Default)]
+struct Builders(HashMap<BuildConfig, Arc<BinaryBuilder>>);
+
+impl Builders {
+    fn prepare<W: WriteColor>(
+        &mut self,
+        entries: &[ExpandedEntry<W>],
+        cfg: &Config<W>,
+    ) -> BatchResult<()> {
+        for entry in entries.iter().filter(|entry| !entry.is_skipped()) {
+            if let hash_map::Entry::Vacant(slot) = self.0.entry(entry_build(entry, cfg)) {
+                let builder = BinaryBuilder::cached(slot.key())?;
+                slot.insert(builder);
+            }
         }
+        Ok(())
     }
+
+    fn get<W: WriteColor>(&self, entry: &ExpandedEntry<W>, cfg: &Config<W>) -> &BinaryBuilder {
+        &self.0[&entry_build(entry, cfg)]
+    }
+}
+
+/// The features set for the entry replace the ones from the batch configuration.
+fn entry_build<W: WriteColor>(entry: &ExpandedEntry<W>, cfg: &Config<W>) -> BuildConfig {
+    let build = cfg.build().clone();
+    match entry.features() {
+        Some(features) => build.with_features(features.clone()),
+        None => build,
+    }
+}
+
+fn run_entry<W: WriteColor>(
+    entry: ExpandedEntry<W>,
+    builders: &Builders,
+    cfg: &Config<W>,
+) -> (String, EntryOutput<W>) {
+    let name = entry.name();
+    if entry.is_skipped() {
+        return (name, entry.skip(cfg));
+    }
+    let builder = builders.get(&entry, cfg);
+    (name, entry.run(builder, cfg))
+}
+
+fn run_entries<W: WriteColor + Send>(
+    entries: Vec<ExpandedEntry<W>>,
+    builders: &Builders,
+    cfg: &Config<W>,
+) -> Vec<(String, EntryOutput<W>)> {
+    let jobs = cfg.jobs().min(entries.len());
+    if jobs <= 1 {
+        return entries
+            .into_iter()
+            .map(|entry| run_entry(entry, builders, cfg))
+            .collect();
+    }
+
+    // Workers pick the entries one by one from the shared queue; the index is stored
+    // with every result, so that they can be restored to the original order afterwards.
+    let queue = Mutex::new(entries.into_iter().enumerate());
+    let mut results: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap_or_else(|err| err.into_inner()).next();
+                        let (index, entry) = match next {
+                            Some(next) => next,
+                            None => break done,
+                        };
+                        let (name, output) = run_entry(entry, builders, cfg);
+                        done.push((index, name, output));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(index, _, _)| *index);
+    results
+        .into_iter()
+        .map(|(_, name, output)| (name, output))
+        .collect()
 }
@@ -0,0 +1,299 @@
+//! Parsing the `rustc` command line printed by `cargo build --verbose`.
+
+use crate::config::ForwardFlags;
+use crate::result::{error::BatchError, BatchResult};
+
+/// Flags which take a value, either attached (`-Lpath`, `--cfg=x`) or as the next argument.
+const WITH_VALUE: &[&str] = &[
+    "-L",
+    "-l",
+    "-o",
+    "-A",
+    "-W",
+    "-D",
+    "-F",
+    "-C",
+    "-Z",
+    "--cfg",
+    "--check-cfg",
+    "--extern",
+    "--crate-name",
+    "--crate-type",
+    "--edition",
+    "--emit",
+    "--print",
+    "--out-dir",
+    "--explain",
+    "--target",
+    "--cap-lints",
+    "--error-format",
+    "--json",
+    "--color",
+    "--sysroot",
+    "--remap-path-prefix",
+    "--diagnostic-width",
+    "--force-warn",
+];
+
+const WITHOUT_VALUE: &[&str] = &["-g", "-O", "--test", "-v", "--verbose"];
+
+/// Flags describing the crate being built by cargo, which make no sense for the entries.
+const CRATE_SPECIFIC: &[&str] = &[
+    "-o",
+    "--crate-name",
+    "--crate-type",
+    "--emit",
+    "--print",
+    "--out-dir",
+    "--error-format",
+    "--json",
+    "--color",
+    // the target is taken from the build configuration instead
+    "--target",
+];
+
+/// Codegen options which are specific to the crate being built by cargo.
+const CRATE_SPECIFIC_CODEGEN: &[&str] = &["metadata", "extra-filename", "incremental"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Flag {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Flag {
+    fn is_crate_specific(&self) -> bool {
+        if CRATE_SPECIFIC.contains(&self.name.as_str()) {
+            return true;
+        }
+        match (self.name.as_str(), &self.value) {
+            ("-C", Some(value)) => {
+                let option = value.split('=').next().unwrap_or_default();
+                CRATE_SPECIFIC_CODEGEN.contains(&option)
+            }
+            _ => false,
+        }
+    }
+
+    fn into_args(self) -> Vec<String> {
+        match self.value {
+            // long flags are kept as a single argument, so that they can be found by the prefix
+            Some(value) if self.name.starts_with("--") => vec![format!("{}={}", self.name, value)],
+            Some(value) => vec![self.name, value],
+            None => vec![self.name],
+        }
+    }
+}
+
+/// Split the command line into words, following the shell quoting rules used by cargo.
+pub fn split(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_owned()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // inside the double quotes, backslash escapes only the special characters
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_owned()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_owned()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_owned()),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn is_env_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+/// Parse the flags of the command, skipping the environment, the program and the source file.
+pub fn parse(words: &[String]) -> BatchResult<Vec<Flag>> {
+    let mut words = words
+        .iter()
+        .skip_while(|word| is_env_assignment(word))
+        // the program itself
+        .skip(1);
+    let mut flags = Vec::new();
+    while let Some(word) = words.next() {
+        if !word.starts_with('-') {
+            // the source file of the crate
+            continue;
+        }
+        let (name, value) = match word.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+            _ if !word.starts_with("--") && word.len() > 2 && word.is_char_boundary(2) => {
+                let (name, value) = word.split_at(2);
+                (name, Some(value.to_owned()))
+            }
+            _ => (word.as_str(), None),
+        };
+        if WITH_VALUE.contains(&name) {
+            let value = match value {
+                Some(value) => value,
+                None => words
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| BatchError::UnhandledFlag(format!("{} without value", name)))?,
+            };
+            flags.push(Flag {
+                name: name.to_owned(),
+                value: Some(value),
+            });
+        } else if WITHOUT_VALUE.contains(&name) && value.is_none() {
+            flags.push(Flag {
+                name: name.to_owned(),
+                value: None,
+            });
+        } else {
+            return Err(BatchError::UnhandledFlag(word.clone()));
+        }
+    }
+    Ok(flags)
+}
+
+/// Arguments of the captured command which should be passed when building the entries.
+pub fn forwarded(line: &str, forward: &ForwardFlags) -> BatchResult<Vec<String>> {
+    let words = split(line)
+        .map_err(|err| BatchError::Probe(format!("malformed rustc command: {}", err)))?;
+    Ok(parse(&words)?
+        .into_iter()
+        .filter(|flag| !flag.is_crate_specific() && forward.forwards(&flag.name))
+        .flat_map(Flag::into_args)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn flag(name: &str, value: Option<&str>) -> Flag {
+        Flag {
+            name: name.to_owned(),
+            value: value.map(String::from),
+        }
+    }
+
+    #[test]
+    fn split_quoting() {
+        assert_eq!(
+            split(r#"rustc --cfg 'feature="std"' -L "dir with space" a\ b "\"\$\\\x""#),
+            Ok(words(&[
+                "rustc",
+                "--cfg",
+                r#"feature="std""#,
+                "-L",
+                "dir with space",
+                "a b",
+                r#""$\\x"#,
+            ]))
+        );
+        assert_eq!(split("  ''  a''b  "), Ok(words(&["", "ab"])));
+        assert!(split("'unterminated").is_err());
+        assert!(split("\"unterminated").is_err());
+        assert!(split("trailing\\").is_err());
+    }
+
+    #[test]
+    fn parse_values() {
+        let command = words(&[
+            "CARGO=cargo",
+            "rustc",
+            "--cfg=attached",
+            "--cfg",
+            "separate",
+            "-Lattached",
+            "-L",
+            "separate",
+            "src/lib.rs",
+            "-O",
+        ]);
+        assert_eq!(
+            parse(&command).unwrap(),
+            [
+                flag("--cfg", Some("attached")),
+                flag("--cfg", Some("separate")),
+                flag("-L", Some("attached")),
+                flag("-L", Some("separate")),
+                flag("-O", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_unhandled() {
+        for command in [
+            &["rustc", "--unknown"][..],
+            &["rustc", "-O3"],
+            &["rustc", "--test=yes"],
+            &["rustc", "--cfg"],
+        ] {
+            match parse(&words(command)) {
+                Err(BatchError::UnhandledFlag(_)) => {}
+                other => panic!("Expected unhandled flag for {:?}, got {:?}", command, other),
+            }
+        }
+    }
+
+    #[test]
+    fn forwarded_flags() {
+        let line = "rustc --crate-name probe --edition=2021 src/bin/probe.rs -C metadata=abc \
+                    -C opt-level=3 --cfg x --target x86_64-unknown-linux-gnu -L deps";
+        assert_eq!(
+            forwarded(line, &ForwardFlags::deny(Vec::<String>::new())).unwrap(),
+            words(&[
+                "--edition=2021",
+                "-C",
+                "opt-level=3",
+                "--cfg=x",
+                "-L",
+                "deps"
+            ])
+        );
+        assert_eq!(
+            forwarded(line, &ForwardFlags::allow(["-L"])).unwrap(),
+            words(&["-L", "deps"])
+        );
+    }
+}
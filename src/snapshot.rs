@@ -1,8 +1,9 @@
 use crate::{
-    config::Update,
-    logging,
+    cargo_rustc::in_manifest_dir,
+    config::{Config, Update},
     mismatch::{match_with_backslashes, CompileFailMismatch, LocalOutput, RunMismatch},
     normalize::diagnostics,
+    reporter::Reporter,
     result::{
         error::NoExpected,
         error::{EntryError, EntryFailed},
@@ -13,33 +14,68 @@ use ron::{
     de::from_str,
     ser::{to_string_pretty, PrettyConfig},
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{
-    convert::{Infallible, TryInto},
+    convert::Infallible,
     fs::{create_dir_all, read_to_string, write},
     process::Output,
 };
 use termcolor::WriteColor;
 
-pub fn check_compile_fail(
-    path: &Path,
+pub fn check_compile_fail<W: WriteColor>(
+    stderr_path: &Path,
     output: Output,
-    update_mode: Update,
-    log: &mut impl WriteColor,
+    cfg: &Config<W>,
+    log: &mut dyn WriteColor,
 ) -> EntryResult<()> {
     // early exit if the entry has indeed compiled
     if output.status.success() {
-        logging::unexpected_build_success(log)?;
+        cfg.reporter().unexpected_build_success(log)?;
         return Err(EntryFailed::ShouldNotCompile);
     }
 
-    let variations = diagnostics(&output.stderr);
+    check_stderr(
+        stderr_path,
+        &output.stderr,
+        cfg,
+        log,
+        EntryFailed::CompileFailMismatch,
+    )
+}
+
+pub fn check_warnings<W: WriteColor>(
+    stderr_path: &Path,
+    output: Output,
+    cfg: &Config<W>,
+    log: &mut dyn WriteColor,
+) -> EntryResult<()> {
+    // no warnings, and none were expected - there's nothing to snapshot
+    if !in_manifest_dir(stderr_path).exists() && diagnostics(&output.stderr).preferred().is_empty()
+    {
+        return Ok(());
+    }
+
+    check_stderr(
+        stderr_path,
+        &output.stderr,
+        cfg,
+        log,
+        EntryFailed::WarningsMismatch,
+    )
+}
+
+fn check_stderr<W: WriteColor>(
+    stderr_path: &Path,
+    stderr: &[u8],
+    cfg: &Config<W>,
+    log: &mut dyn WriteColor,
+    mismatch: fn(CompileFailMismatch) -> EntryFailed,
+) -> EntryResult<()> {
+    let variations = diagnostics(stderr);
     let preferred = variations.preferred();
     // In this case, the expected output is simply a string - let's read it!
-    let stderr_path = path.with_extension("stderr");
-
     // But first, check if it ever exists...
-    if !stderr_path.exists() {
+    if !in_manifest_dir(stderr_path).exists() {
         // logging::fail_output(log, Warn, &build_stdout);
 
         // both write_wip and write_overwrite are "always-fallible", and this is statically guaranteed
@@ -47,14 +83,14 @@ pub fn check_compile_fail(
         // with stabilization of "never" type, we can guarantee this here, too
         // but for now, just trust us
         // (joking... you can always check the signatures)
-        match update_mode {
-            Update::Wip => write_wip(&stderr_path, preferred, log)?,
-            Update::Overwrite => write_overwrite(&stderr_path, preferred, log)?,
+        match cfg.update_mode() {
+            Update::Wip => write_wip(stderr_path, preferred, cfg.reporter(), log)?,
+            Update::Overwrite => write_overwrite(stderr_path, preferred, cfg.reporter(), log)?,
         };
     }
 
     // ok, well - the file does exist, but does it contain the same that we've got?
-    let expected = read_to_string(&stderr_path)
+    let expected = read_to_string(in_manifest_dir(stderr_path))
         .map_err(EntryError::ReadExpected)?
         .replace("\r\n", "\n");
 
@@ -62,31 +98,27 @@ pub fn check_compile_fail(
         return Ok(());
     }
 
-    match update_mode {
+    match cfg.update_mode() {
         Update::Wip => {
-            logging::mismatch(log, &expected, preferred)?;
-            Err(EntryFailed::CompileFailMismatch(CompileFailMismatch::new(
-                expected, preferred,
-            )))
+            cfg.reporter()
+                .mismatch(log, &expected, preferred, cfg.mismatch_view())?;
+            Err(mismatch(CompileFailMismatch::new(expected, preferred)))
+        }
+        Update::Overwrite => {
+            write_overwrite(stderr_path, preferred, cfg.reporter(), log).map(|_| ())
         }
-        Update::Overwrite => write_overwrite(&stderr_path, preferred, log).map(|_| ()),
     }
 }
 
-pub fn check_run_match(
-    path: &Path,
-    output: Output,
-    update_mode: Update,
-    log: &mut impl WriteColor,
+pub fn check_run_match<W: WriteColor>(
+    snapshot_path: &Path,
+    output: LocalOutput,
+    cfg: &Config<W>,
+    log: &mut dyn WriteColor,
 ) -> EntryResult<()> {
-    // TODO propagate error
-    let output: LocalOutput = output.try_into().expect("No status code");
-
     // In this case, the expected output is the file representing the output - let's read it!
-    let snapshot_path = path.with_extension("snapshot");
-
     // But first, check if it ever exists...
-    if !snapshot_path.exists() {
+    if !in_manifest_dir(snapshot_path).exists() {
         // logging::fail_output(log, Warn, &build_stdout);
 
         let data =
@@ -96,14 +128,14 @@ pub fn check_run_match(
         // with stabilization of "never" type, we can guarantee this here, too
         // but for now, just trust us
         // (joking... you can always check the signatures)
-        match update_mode {
-            Update::Wip => write_wip(&snapshot_path, &data, log)?,
-            Update::Overwrite => write_overwrite(&snapshot_path, &data, log)?,
+        match cfg.update_mode() {
+            Update::Wip => write_wip(snapshot_path, &data, cfg.reporter(), log)?,
+            Update::Overwrite => write_overwrite(snapshot_path, &data, cfg.reporter(), log)?,
         };
     }
 
     // ok, well - the file does exist, but does it contain the same that we've got?
-    let string = &read_to_string(&snapshot_path)
+    let string = &read_to_string(in_manifest_dir(snapshot_path))
         .map_err(EntryError::ReadExpected)?
         .replace("\r\n", "\n");
     let expected = from_str(string).expect("Deserialization failed");
@@ -113,32 +145,45 @@ pub fn check_run_match(
     }
 
     let data = to_string_pretty(&output, PrettyConfig::default()).expect("Serialization failed");
-    match update_mode {
+    match cfg.update_mode() {
         Update::Wip => {
-            logging::mismatch(log, string, &data)?;
+            cfg.reporter()
+                .mismatch(log, string, &data, cfg.mismatch_view())?;
             Err(EntryFailed::RunMismatch(RunMismatch::new(expected, output)))
         }
         Update::Overwrite => {
             // TODO propagate the serialization-deserialization errors
-            write_overwrite(&snapshot_path, &data, log).map(|_| ())
+            write_overwrite(snapshot_path, &data, cfg.reporter(), log).map(|_| ())
         }
     }
 }
 
-fn write_wip(path: &Path, content: &str, log: &mut impl WriteColor) -> EntryResult<Infallible> {
-    let wip_dir = Path::new("wip");
-    create_dir_all(wip_dir)?;
+const WIP_DIR: &str = "wip";
+
+/// Path in the WIP folder, where the output for the given snapshot is written.
+pub fn wip_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .expect("Failed to write expected content to WIP folder - corrupt path");
+    Path::new(WIP_DIR).join(name)
+}
+
+fn write_wip(
+    path: &Path,
+    content: &str,
+    reporter: &dyn Reporter,
+    log: &mut dyn WriteColor,
+) -> EntryResult<Infallible> {
+    let wip_dir = in_manifest_dir(Path::new(WIP_DIR));
+    create_dir_all(&wip_dir)?;
 
     let gitignore_path = wip_dir.join(".gitignore");
     write(gitignore_path, "*\n")?;
 
-    let stderr_name = path
-        .file_name()
-        .expect("Failed to write expected content to WIP folder - corrupt path");
-    let wip_path = wip_dir.join(stderr_name);
-    logging::log_wip_write(log, &wip_path, path, content)?;
+    let wip_path = wip_path(path);
+    reporter.wip_write(log, &wip_path, path, content)?;
 
-    write(wip_path, content).map_err(EntryError::WriteExpected)?;
+    write(in_manifest_dir(&wip_path), content).map_err(EntryError::WriteExpected)?;
 
     Err(EntryFailed::ExpectedNotExist(NoExpected::ToWip(
         content.to_owned(),
@@ -148,11 +193,12 @@ fn write_wip(path: &Path, content: &str, log: &mut impl WriteColor) -> EntryResu
 fn write_overwrite(
     path: &Path,
     content: &str,
-    log: &mut impl WriteColor,
+    reporter: &dyn Reporter,
+    log: &mut dyn WriteColor,
 ) -> EntryResult<Infallible> {
-    logging::log_overwrite(log, path, content)?;
+    reporter.overwrite(log, path, content)?;
 
-    write(path, content).map_err(EntryError::WriteExpected)?;
+    write(in_manifest_dir(path), content).map_err(EntryError::WriteExpected)?;
 
     Err(EntryFailed::ExpectedNotExist(NoExpected::Direct(
         content.to_owned(),
@@ -0,0 +1,100 @@
+//! Exit status of the entry process, in the form which can be stored in snapshot.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::ExitStatus;
+
+/// The way the entry process has terminated.
+///
+/// In snapshots, the exit code is stored as a plain number (as it always was),
+/// and the termination by signal is stored as a structure with the signal number and name.
+#[derive(PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Status {
+    /// The process has exited with the given code.
+    Code(i32),
+    /// The process was terminated by signal (only possible on Unix).
+    Signal { signal: i32, name: String },
+}
+
+impl Status {
+    pub fn success(&self) -> bool {
+        *self == Status::Code(0)
+    }
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Status::Code(code) => Some(*code),
+            Status::Signal { .. } => None,
+        }
+    }
+}
+
+impl From<ExitStatus> for Status {
+    fn from(status: ExitStatus) -> Self {
+        if let Some(code) = status.code() {
+            return Status::Code(code);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(signal) = status.signal() {
+                return Status::Signal {
+                    signal,
+                    name: signal_name(signal).to_owned(),
+                };
+            }
+        }
+        // on Unix, the process which has been waited for either exited or was killed,
+        // and on other platforms there's always an exit code
+        unreachable!("Process has neither exit code nor terminating signal")
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Code(code) => write!(f, "exit code {}", code),
+            Status::Signal { signal, name } => write!(f, "signal {} ({})", signal, name),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn signal_name(signal: i32) -> &'static str {
+    // these numbers are the same on every Unix we care about
+    match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        _ => platform_signal_name(signal),
+    }
+}
+
+#[cfg(all(unix, any(target_os = "linux", target_os = "android")))]
+fn platform_signal_name(signal: i32) -> &'static str {
+    match signal {
+        7 => "SIGBUS",
+        10 => "SIGUSR1",
+        12 => "SIGUSR2",
+        _ => "UNKNOWN",
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn platform_signal_name(signal: i32) -> &'static str {
+    match signal {
+        10 => "SIGBUS",
+        30 => "SIGUSR1",
+        31 => "SIGUSR2",
+        _ => "UNKNOWN",
+    }
+}
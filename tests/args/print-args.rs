@@ -0,0 +1,6 @@
+fn main() {
+    for arg in std::env::args().skip(1) {
+        println!("arg: {}", arg);
+    }
+    println!("env: {:?}", std::env::var("BATCH_RUN_TEST_VAR").ok());
+}
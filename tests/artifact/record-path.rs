@@ -0,0 +1,11 @@
+use std::{env, fs::OpenOptions, io::Write};
+
+fn main() {
+    let list = env::var("BATCH_RUN_ARTIFACTS").unwrap();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(list)
+        .unwrap();
+    writeln!(file, "{}", env::current_exe().unwrap().display()).unwrap();
+}
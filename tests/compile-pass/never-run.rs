@@ -0,0 +1,4 @@
+fn main() {
+    // this would hang if the entry was ever run
+    loop {}
+}
@@ -0,0 +1,8 @@
+use std::env;
+
+fn main() {
+    let count: usize = env::args().nth(1).unwrap().parse().unwrap();
+    for line in 1..=count {
+        println!("line {}", line);
+    }
+}
@@ -0,0 +1,6 @@
+// `async fn` is not available in Rust 2015
+async fn ready() {}
+
+fn main() {
+    let _ = ready();
+}
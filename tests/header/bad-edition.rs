@@ -0,0 +1,2 @@
+// edition:2077
+fn main() {}
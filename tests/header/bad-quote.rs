@@ -0,0 +1,3 @@
+// run-args: "unterminated
+
+fn main() {}
@@ -0,0 +1,15 @@
+// Header directives are applied to the entry as if they were set with `EntryBuilder`.
+// edition:2015
+// compile-flags: --cfg 'custom_flag="on"'
+// run-args: first "second arg"
+// exit-status: 3
+
+fn main() {
+    // `async` is not a keyword in Rust 2015
+    let async = std::env::args().skip(1).collect::<Vec<_>>();
+    println!("args: {:?}", async);
+    if cfg!(custom_flag = "on") {
+        println!("custom flag is set");
+    }
+    std::process::exit(3);
+}
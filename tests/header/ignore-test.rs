@@ -0,0 +1,4 @@
+// ignore-test
+fn main() {
+    panic!("This entry must never run");
+}
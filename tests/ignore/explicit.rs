@@ -0,0 +1,3 @@
+fn main() {
+    panic!("This entry must never run");
+}
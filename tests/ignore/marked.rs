@@ -0,0 +1,4 @@
+// batch_run: ignore = "fails on purpose"
+fn main() {
+    let x: u32 = "not a number";
+}
@@ -0,0 +1,5 @@
+use std::{thread, time::Duration};
+
+fn main() {
+    thread::sleep(Duration::from_millis(0));
+}
@@ -0,0 +1,4 @@
+#!/bin/sh
+# records the linked files before linking them with the system linker
+echo "$@" >> "$BATCH_RUN_LINKER_LOG"
+exec cc "$@"
@@ -0,0 +1 @@
+compile_error!("actual error");
@@ -0,0 +1,6 @@
+fn main() {
+    println!("first line");
+    println!("second line, changed");
+    println!("third line");
+    std::process::exit(2);
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let async = "`async` is not a keyword in Rust 2015";
+    println!("{}", async);
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let _ = std::mem::size_of::<batch_run::config::BuildConfig>();
+    assert!(glob::Pattern::new("*.rs").unwrap().matches("main.rs"));
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let values: Vec<u32> = Vec::new();
+    panic!("Expected panic with {} values", values.len());
+}
@@ -0,0 +1,4 @@
+fn main() {
+    assert_eq!(std::env::var("BATCH_RUN_WRAPPED").as_deref(), Ok("1"));
+    assert_eq!(std::env::args().nth(1).as_deref(), Some("--entry-arg"));
+}
@@ -0,0 +1,4 @@
+#[cfg(not(batch_run_cfg))]
+compile_error!("built without `--cfg batch_run_cfg`");
+
+fn main() {}
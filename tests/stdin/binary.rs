@@ -0,0 +1,7 @@
+use std::io::{stdin, Read};
+
+fn main() {
+    let mut input = Vec::new();
+    stdin().read_to_end(&mut input).unwrap();
+    println!("Read {:?}", input);
+}
@@ -0,0 +1,7 @@
+use std::io::{stdin, Read};
+
+fn main() {
+    let mut input = String::new();
+    stdin().read_to_string(&mut input).unwrap();
+    println!("Read {} bytes", input.len());
+}
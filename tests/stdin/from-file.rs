@@ -0,0 +1,7 @@
+use std::io::{stdin, BufRead};
+
+fn main() {
+    for line in stdin().lock().lines() {
+        println!("{}", line.unwrap().to_uppercase());
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    println!("This entry intentionally has no snapshot");
+}
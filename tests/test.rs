@@ -1,3 +1,21 @@
+use batch_run::config::{
+    BuildConfig, Config, Edition, Features, ForwardFlags, MismatchView, Probe, WriterBuilder,
+};
+use batch_run::reporter::Reporter;
+use batch_run::result::{
+    error::{BatchError, ConfigError, EntryError, EntryFailed},
+    EntryResult, Input, Line, Status, Summary,
+};
+use batch_run::Expected;
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use termcolor::WriteColor;
+
 #[test]
 fn basic() {
     let t = batch_run::Batch::new();
@@ -13,3 +31,895 @@ fn ui() {
     t.run_match("tests/ui-runner/main.rs");
     t.run().unwrap().assert_all_ok();
 }
+
+#[test]
+fn timeout() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/timeout/*.rs")
+        .timeout(Duration::from_millis(500));
+    let res = t.run().unwrap();
+    match res.errors().unwrap().as_slice() {
+        [(_, EntryFailed::Timeout(_))] => {}
+        errors => panic!("Expected a single timeout, got {:?}", errors),
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn timeout_grandchild() {
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/timeout-grandchild/detached.rs")
+        .timeout(Duration::from_millis(500));
+    let start = Instant::now();
+    let res = t.run_with_config(Config::default().with_buffer()).unwrap();
+    match res.errors().unwrap().as_slice() {
+        [(_, EntryFailed::Timeout(_))] => {}
+        errors => panic!("Expected a single timeout, got {:?}", errors),
+    }
+    assert!(start.elapsed() < Duration::from_secs(20));
+}
+
+#[test]
+fn stdin() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/stdin/from-file.rs");
+    t.run_match("tests/stdin/from-api.rs").stdin("input\n");
+    t.run().unwrap().assert_all_ok();
+}
+
+#[test]
+fn stdin_exact() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/stdin/binary.rs")
+        .stdin(vec![0xff, 0, b'\r', b'\n']);
+    // the same entry as in `stdin`, fed without the trailing newline
+    t.run_match("tests/stdin/from-api.rs").stdin("input");
+    let res = t.run().unwrap();
+    let mismatch = match res.errors().unwrap().as_slice() {
+        [(_, EntryFailed::RunMismatch(mismatch))] => mismatch,
+        errors => panic!("Expected a single run mismatch, got {:?}", errors),
+    };
+    assert_eq!(
+        mismatch.expected().stdin(),
+        Some(&Input::Text("input\n".into()))
+    );
+    assert_eq!(
+        mismatch.actual().stdin().map(Input::as_bytes),
+        Some(&b"input"[..])
+    );
+    assert_eq!(
+        mismatch.diff().stdin[0].lines,
+        vec![
+            Line::Removed("input\\n".into()),
+            Line::Added("input".into())
+        ]
+    );
+}
+
+#[test]
+fn args() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/args/print-args.rs")
+        .args(vec!["first", "with space"])
+        .env("BATCH_RUN_TEST_VAR", "value");
+    t.run().unwrap().assert_all_ok();
+}
+
+#[test]
+fn options() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/options/edition-2015.rs")
+        .edition(Edition::E2015)
+        .snapshot_dir("tests/options/snapshots");
+    t.run_match("tests/options/exit-code.rs")
+        .exit_code(3)
+        .snapshot_dir("tests/options/snapshots");
+    t.run().unwrap().assert_all_ok();
+}
+
+#[test]
+fn compile_pass() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    t.compile_pass("tests/compile-pass/never-run.rs").link(true);
+    t.compile_pass("tests/compile-pass/warnings.rs")
+        .snapshot_warnings(true);
+    t.run().unwrap().assert_all_ok();
+}
+
+#[test]
+fn run_pass() {
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/run-pass/*.rs");
+    let res = t.run().unwrap();
+    match res.errors().unwrap().as_slice() {
+        [(
+            path,
+            EntryFailed::RunFailed {
+                status: Status::Code(101),
+                stderr_tail,
+            },
+        )] if path.ends_with("panic.rs") && stderr_tail.contains("Expected panic") => {}
+        errors => panic!("Expected a single run failure, got {:?}", errors),
+    }
+}
+
+#[test]
+fn run_fail() {
+    let t = batch_run::Batch::new();
+    t.run_fail("tests/run-fail/panic.rs")
+        .panic_message("Expected panic with 0 values");
+    t.run_fail("tests/run-fail/exit-code.rs").exit_code(3);
+    t.run_fail("tests/run-fail/success.rs");
+    t.run_fail("tests/run-fail/panic.rs")
+        .panic_message("Unexpected panic");
+    let res = t.run().unwrap();
+    match res.errors().unwrap().as_slice() {
+        [(_, EntryFailed::ShouldFail), (_, EntryFailed::PanicMismatch { .. })] => {}
+        errors => panic!("Expected two failures, got {:?}", errors),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn signal() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/signal/abort.rs");
+    t.run_pass("tests/signal/abort.rs");
+    let res = t.run().unwrap();
+    match res.errors().unwrap().as_slice() {
+        [(
+            _,
+            EntryFailed::RunFailed {
+                status: Status::Signal { signal: 6, .. },
+                ..
+            },
+        )] => {}
+        errors => panic!("Expected a single run failure, got {:?}", errors),
+    }
+}
+
+#[test]
+fn mismatch() {
+    let t = batch_run::Batch::new();
+    t.compile_fail("tests/mismatch/stale-error.rs");
+    t.run_match("tests/mismatch/stale-output.rs");
+    let res = t.run().unwrap();
+    let errors = res.errors().unwrap();
+
+    let mismatch = match errors.as_slice() {
+        [(_, EntryFailed::CompileFailMismatch(mismatch)), _] => mismatch,
+        errors => panic!("Expected compile-fail mismatch, got {:?}", errors),
+    };
+    assert!(mismatch.expected().starts_with("error: expected error"));
+    assert!(mismatch.actual().starts_with("error: actual error"));
+    let hunks = mismatch.hunks();
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(
+        hunks[0].lines[0],
+        Line::Removed("error: expected error".into())
+    );
+
+    let mismatch = match errors.as_slice() {
+        [_, (_, EntryFailed::RunMismatch(mismatch))] => mismatch,
+        errors => panic!("Expected run mismatch, got {:?}", errors),
+    };
+    assert_eq!(mismatch.expected().status(), &Status::Code(0));
+    assert_eq!(mismatch.actual().status(), &Status::Code(2));
+    let diff = mismatch.diff();
+    assert_eq!(diff.status, Some((Status::Code(0), Status::Code(2))));
+    assert!(diff.stderr.is_empty());
+    assert_eq!(diff.stdout.len(), 1);
+    assert_eq!(
+        (diff.stdout[0].expected_start, diff.stdout[0].actual_start),
+        (1, 1)
+    );
+    assert_eq!(
+        diff.stdout[0].lines,
+        vec![
+            Line::Context("first line".into()),
+            Line::Removed("second line".into()),
+            Line::Added("second line, changed".into()),
+            Line::Context("third line".into()),
+        ]
+    );
+}
+
+#[test]
+fn junit() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    t.run_match("tests/mismatch/stale-output.rs");
+    let res = t.run().unwrap();
+    let mut report = Vec::new();
+    res.write_junit("junit", &mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+
+    assert!(report.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(report.contains(r#"<testsuite name="junit" tests="2" failures="1" errors="0""#));
+    assert!(
+        report.contains(r#"<testcase name="tests/compile-pass/never-run.rs" classname="junit""#)
+    );
+    assert!(report.contains(r#"<failure type="RunMismatch" message="Runtime output mismatch">"#));
+    assert!(report.contains("status: expected exit code 0, got exit code 2&#10;stdout:&#10;"));
+    assert!(report.contains(" first line&#10;-second line&#10;+second line, changed&#10;"));
+    assert!(report.trim_end().ends_with("</testsuite>"));
+}
+
+/// Snapshot of `tests/diff/lines.rs`, as it would be written for the given lines.
+fn lines_snapshot(lines: &[String]) -> String {
+    let mut snapshot = format!(
+        "(\n    args: [\n        \"{}\",\n    ],\n    status: 0,\n    stdout: [\n",
+        lines.len()
+    );
+    for line in lines {
+        snapshot.push_str(&format!("        \"{}\",\n", line));
+    }
+    snapshot.push_str("    ],\n    stderr: [],\n)");
+    snapshot
+}
+
+/// Run `tests/diff/lines.rs` against the given snapshot and return its log without colors.
+fn diff_log(name: &str, expected: &[String], view: MismatchView) -> String {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("lines.snapshot"), lines_snapshot(expected)).unwrap();
+
+    let t = batch_run::Batch::new();
+    t.run_match("tests/diff/lines.rs")
+        .arg(expected.len().to_string())
+        .snapshot_dir(&dir);
+    let cfg = Config::default()
+        .with_writer(WriterBuilder::buffer())
+        .with_mismatch_view(view);
+    let res = t.run_with_config(cfg).unwrap();
+    let output = &res.outputs()[0].1;
+    assert!(matches!(output.err(), Some(EntryFailed::RunMismatch(_))));
+
+    let log = String::from_utf8_lossy(output.log().unwrap().as_slice()).into_owned();
+    // strip the color escape sequences
+    let mut plain = String::new();
+    let mut chars = log.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+#[test]
+fn diff_view() {
+    let mut expected: Vec<_> = (1..=20).map(|line| format!("line {}", line)).collect();
+    expected[9] = "line 10, changed".into();
+    let log = diff_log("diff-view", &expected, MismatchView::Diff);
+
+    // the changed line, with three lines of context on both sides
+    let hunk = [
+        "@@ -13,7 +13,7 @@",
+        r#"         "line 7","#,
+        r#"         "line 8","#,
+        r#"         "line 9","#,
+        r#"-        "line 10, changed","#,
+        r#"+        "line 10","#,
+        r#"         "line 11","#,
+        r#"         "line 12","#,
+        r#"         "line 13","#,
+        "",
+    ]
+    .join("\n");
+    assert!(log.contains(&hunk), "{}", log);
+    assert_eq!(log.matches("@@ -").count(), 1);
+    assert!(!log.contains(r#""line 6""#));
+    assert!(!log.contains(r#""line 14""#));
+}
+
+#[test]
+fn changed_arg() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("changed-arg");
+    std::fs::create_dir_all(&dir).unwrap();
+    let expected: Vec<_> = (1..=3).map(|line| format!("line {}", line)).collect();
+    std::fs::write(dir.join("lines.snapshot"), lines_snapshot(&expected)).unwrap();
+
+    // the extra argument is ignored by the entry, so only the arguments differ
+    let t = batch_run::Batch::new();
+    t.run_match("tests/diff/lines.rs")
+        .args(vec!["3", "extra"])
+        .snapshot_dir(&dir);
+    let res = t.run_with_config(Config::default().with_buffer()).unwrap();
+    let mismatch = match res.errors().unwrap().as_slice() {
+        [(_, EntryFailed::RunMismatch(mismatch))] => mismatch,
+        errors => panic!("Expected a single run mismatch, got {:?}", errors),
+    };
+    let diff = mismatch.diff();
+    assert_eq!(diff.args.len(), 1);
+    assert_eq!(
+        diff.args[0].lines,
+        vec![Line::Context("3".into()), Line::Added("extra".into())]
+    );
+    assert!(diff.env.is_empty() && diff.stdin.is_empty());
+    assert!(diff.stdout.is_empty() && diff.stderr.is_empty());
+    assert_eq!(diff.status, None);
+    assert_eq!(diff.to_string(), "args:\n@@ -1,1 +1,2 @@\n 3\n+extra\n");
+}
+
+#[test]
+fn full_view() {
+    let mut expected: Vec<_> = (1..=20).map(|line| format!("line {}", line)).collect();
+    expected[9] = "line 10, changed".into();
+    let log = diff_log("full-view", &expected, MismatchView::Full);
+
+    assert!(log.contains("EXPECTED:"));
+    assert!(log.contains("ACTUAL:"));
+    assert!(!log.contains("@@ -"));
+    // every line is shown, including the unchanged ones
+    assert_eq!(log.matches(r#""line 1","#).count(), 2);
+    assert_eq!(log.matches(r#""line 10, changed","#).count(), 1);
+    assert_eq!(log.matches(r#""line 10","#).count(), 1);
+}
+
+#[test]
+fn diff_over_table_size() {
+    // every odd line is changed, so the changed parts are too large for the detailed diff
+    let expected: Vec<_> = (1..=2100)
+        .map(|line| match line % 2 {
+            0 => format!("line {}", line),
+            _ => format!("other {}", line),
+        })
+        .collect();
+    let log = diff_log("diff-over-table-size", &expected, MismatchView::Diff);
+
+    // the whole changed part is shown as replaced, without the common lines in between
+    assert_eq!(log.matches("@@ -").count(), 1);
+    assert!(log.contains("@@ -4,2105 +4,2105 @@"));
+    assert!(log.contains("\n-        \"other 1\",\n+        \"line 1\",\n"));
+    assert!(log.contains("\n-        \"line 2\",\n+        \"line 2\",\n"));
+    assert!(!log.contains("\n         \"line 2\",\n"));
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn events() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    t.run_match("tests/mismatch/stale-output.rs");
+    let events = SharedBuffer::default();
+    let cfg = Config::default().with_events(events.clone());
+    t.run_with_config(cfg).unwrap();
+
+    let events = events.0.lock().unwrap();
+    let events: Vec<serde_json::Value> = std::str::from_utf8(&events)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<_> = events.iter().map(|event| &event["event"]).collect();
+    assert_eq!(
+        kinds,
+        [
+            "batch_started",
+            "entry_started",
+            "entry_finished",
+            "entry_started",
+            "entry_finished",
+            "batch_finished"
+        ]
+    );
+    assert_eq!(events[0]["entries"], 2);
+    assert_eq!(events[1]["path"], "tests/compile-pass/never-run.rs");
+    assert_eq!(events[1]["expected"], "CompilePass");
+    assert_eq!(events[2]["result"], "ok");
+    assert!(events[2]["duration"].is_f64());
+    assert_eq!(events[4]["result"], "RunMismatch");
+    assert_eq!(
+        events[4]["snapshot"],
+        "tests/mismatch/stale-output.snapshot"
+    );
+    assert_eq!(events[5]["passed"], 1);
+    assert_eq!(events[5]["failed"], 1);
+}
+
+#[derive(Clone, Default)]
+struct RecordingReporter(Arc<Mutex<Vec<String>>>);
+
+impl RecordingReporter {
+    fn record(&self, event: String) -> io::Result<()> {
+        self.0.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+impl Reporter for RecordingReporter {
+    fn entry_started(
+        &self,
+        _: &mut dyn WriteColor,
+        path: &Path,
+        expected: Expected,
+    ) -> io::Result<()> {
+        self.record(format!("started {} {:?}", path.display(), expected))
+    }
+    fn mismatch(
+        &self,
+        _: &mut dyn WriteColor,
+        _: &str,
+        _: &str,
+        _: MismatchView,
+    ) -> io::Result<()> {
+        self.record("mismatch".into())
+    }
+    fn entry_finished(
+        &self,
+        _: &mut dyn WriteColor,
+        path: &Path,
+        res: &EntryResult,
+        _: Duration,
+    ) -> io::Result<()> {
+        let result = match res {
+            Ok(()) => "ok",
+            Err(err) => err.kind(),
+        };
+        self.record(format!("finished {} {}", path.display(), result))
+    }
+    fn batch_finished(&self, _: &mut dyn WriteColor, summary: &Summary) -> io::Result<()> {
+        self.record(format!(
+            "batch {} passed, {} failed",
+            summary.passed(),
+            summary.failed()
+        ))
+    }
+}
+
+#[test]
+fn reporter() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    t.run_match("tests/mismatch/stale-output.rs");
+    let reporter = RecordingReporter::default();
+    let cfg = Config::default()
+        .with_buffer()
+        .with_reporter(reporter.clone());
+    t.run_with_config(cfg).unwrap();
+
+    assert_eq!(
+        *reporter.0.lock().unwrap(),
+        [
+            "started tests/compile-pass/never-run.rs CompilePass",
+            "finished tests/compile-pass/never-run.rs ok",
+            "started tests/mismatch/stale-output.rs RunMatch",
+            "mismatch",
+            "finished tests/mismatch/stale-output.rs RunMismatch",
+            "batch 1 passed, 1 failed",
+        ]
+    );
+}
+
+#[test]
+fn summary() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    t.run_match("tests/mismatch/stale-output.rs");
+    t.run_match("tests/summary/no-snapshot.rs");
+    let summary = t.run().unwrap().summary();
+    // the missing snapshot was written to the WIP folder, which should stay clean
+    let wip = Path::new(env!("CARGO_MANIFEST_DIR")).join("wip/no-snapshot.snapshot");
+    std::fs::remove_file(wip).unwrap();
+
+    assert_eq!(summary.total(), 3);
+    assert_eq!(summary.passed(), 1);
+    assert_eq!(summary.failed(), 2);
+    assert_eq!(summary.wip(), 1);
+    assert_eq!(summary.failures()["RunMismatch"], 1);
+    assert_eq!(summary.failures()["ExpectedNotExist"], 1);
+    assert_eq!(
+        summary.failing(),
+        [
+            "tests/mismatch/stale-output.rs",
+            "tests/summary/no-snapshot.rs"
+        ]
+    );
+    let (_, slowest) = summary.slowest().unwrap();
+    assert!(slowest <= summary.duration());
+}
+
+#[test]
+fn filter() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/*.rs");
+    t.run_match("tests/mismatch/stale-output.rs");
+    let cfg = Config::default().with_filter("never-run").with_buffer();
+    let res = t.run_with_config(cfg).unwrap();
+    res.assert_all_ok();
+
+    let outputs = res.outputs();
+    let skipped: Vec<_> = outputs
+        .iter()
+        .map(|(name, output)| (name.as_str(), output.is_skipped()))
+        .collect();
+    assert_eq!(
+        skipped,
+        [
+            ("tests/compile-pass/never-run.rs", false),
+            ("tests/compile-pass/warnings.rs", true),
+            ("tests/mismatch/stale-output.rs", true)
+        ]
+    );
+    // the skipped entries are still mentioned in the log
+    let log = outputs[1].1.log().unwrap().as_slice();
+    assert!(String::from_utf8_lossy(log).contains("skipped"));
+    let summary = res.summary();
+    assert_eq!((summary.passed(), summary.skipped()), (1, 2));
+}
+
+#[test]
+fn filter_from_args() {
+    let cfg = Config::default().with_filter_from([
+        "never-run",
+        "--test-threads",
+        "4",
+        "--format=json",
+        "--skip",
+        "slow",
+        "-q",
+        "stale",
+    ]);
+    assert_eq!(cfg.filter(), ["never-run", "stale"]);
+
+    // everything after `--` is a pattern, even if it looks like a flag
+    let cfg = Config::default().with_filter_from(["--color", "never", "--", "--color", "-x"]);
+    assert_eq!(cfg.filter(), ["--color", "-x"]);
+}
+
+#[test]
+fn ignore() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/ignore/marked.rs");
+    t.run_pass("tests/ignore/explicit.rs").ignore("");
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    let res = t.run().unwrap();
+    res.assert_all_ok();
+
+    let outputs = res.outputs();
+    let ignored: Vec<_> = outputs
+        .iter()
+        .map(|(_, output)| output.ignore_reason())
+        .collect();
+    assert_eq!(ignored, [Some("fails on purpose"), Some(""), None]);
+    let summary = res.summary();
+    assert_eq!((summary.passed(), summary.ignored()), (1, 2));
+}
+
+#[test]
+fn header() {
+    let t = batch_run::Batch::new();
+    t.run_match("tests/header/directives.rs");
+    t.run_pass("tests/header/ignore-test.rs");
+    t.run_pass("tests/header/bad-edition.rs");
+    t.run_pass("tests/header/bad-quote.rs");
+    let res = t.run().unwrap();
+
+    match res.errors().unwrap().as_slice() {
+        [(edition, EntryFailed::Error(EntryError::Header(1, _))), (quote, EntryFailed::Error(EntryError::Header(1, message)))] =>
+        {
+            assert_eq!(edition.as_str(), "tests/header/bad-edition.rs");
+            assert_eq!(quote.as_str(), "tests/header/bad-quote.rs");
+            assert_eq!(message, "malformed run-args: unterminated double quote");
+        }
+        errors => panic!("Expected header errors, got {:?}", errors),
+    }
+    assert_eq!(res.summary().ignored(), 1);
+}
+
+#[test]
+fn batch_edition() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/options/edition-2015.rs");
+    t.compile_pass("tests/edition/async-fn.rs")
+        .edition(Edition::E2018);
+    let cfg = Config::default().with_edition(Edition::E2015);
+    t.run_with_config(cfg).unwrap().assert_all_ok();
+}
+
+#[test]
+fn features() {
+    let t = batch_run::Batch::new();
+    t.compile_pass("tests/compile-pass/never-run.rs");
+    t.run_pass("tests/run-pass/success.rs")
+        .feature_set(Features::default().without_default());
+    let cfg = Config::default().with_features(Features::all());
+    t.run_with_config(cfg).unwrap().assert_all_ok();
+}
+
+#[test]
+fn build_config() {
+    for build in [
+        BuildConfig::new(),
+        BuildConfig::new().with_profile("dev"),
+        BuildConfig::new().with_features(Features::all()),
+    ] {
+        let t = batch_run::Batch::new();
+        t.run_pass("tests/run-pass/success.rs");
+        let cfg = Config::default().with_build(build);
+        t.run_with_config(cfg).unwrap().assert_all_ok();
+    }
+}
+
+#[test]
+fn probe() {
+    for probe in [Probe::Metadata, Probe::SourceBin] {
+        let t = batch_run::Batch::new();
+        t.run_pass("tests/probe/uses-deps.rs");
+        let cfg = Config::default().with_build(BuildConfig::new().with_probe(probe));
+        t.run_with_config(cfg).unwrap().assert_all_ok();
+    }
+}
+
+#[test]
+fn probe_rustflags() {
+    if env::var_os("BATCH_RUN_ENV_TEST").is_none() {
+        // the separate target directory keeps the main one from being rebuilt with other flags
+        let target = Path::new(env!("CARGO_TARGET_TMPDIR")).join("probe-rustflags");
+        run_with_env(
+            "probe_rustflags",
+            &[
+                ("RUSTFLAGS", "--cfg batch_run_cfg"),
+                ("CARGO_TARGET_DIR", target.to_str().unwrap()),
+            ],
+        );
+        return;
+    }
+
+    // the `--cfg` from RUSTFLAGS is passed to the entries with either probe
+    for probe in [Probe::Metadata, Probe::SourceBin] {
+        let t = batch_run::Batch::new();
+        t.compile_pass("tests/rustflags/gated.rs");
+        let cfg = Config::default().with_build(BuildConfig::new().with_probe(probe));
+        t.run_with_config(cfg).unwrap().assert_all_ok();
+    }
+}
+
+#[test]
+fn forwarded_flags() {
+    let build = BuildConfig::new()
+        .with_probe(Probe::SourceBin)
+        .with_features(Features::all());
+
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/probe/uses-deps.rs");
+    let cfg = Config::default().with_build(
+        build
+            .clone()
+            .with_forwarded_flags(ForwardFlags::deny(["--test"])),
+    );
+    t.run_with_config(cfg).unwrap().assert_all_ok();
+
+    // without the dependencies, the entry can't be built
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/probe/uses-deps.rs");
+    let cfg = Config::default()
+        .with_build(build.with_forwarded_flags(ForwardFlags::allow(["--edition"])))
+        .with_buffer();
+    match t.run_with_config(cfg).unwrap().errors().unwrap().as_slice() {
+        [(_, EntryFailed::ShouldCompile(_))] => {}
+        errors => panic!("Expected a build failure, got {:?}", errors),
+    }
+}
+
+#[test]
+#[cfg(all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"))]
+fn target_runner() {
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/probe/uses-deps.rs");
+    t.run_pass("tests/runner/wrapped.rs")
+        .args(vec!["--entry-arg"]);
+    let build = BuildConfig::new()
+        .with_target("x86_64-unknown-linux-gnu")
+        .with_runner(["env", "BATCH_RUN_WRAPPED=1"]);
+    let cfg = Config::default().with_build(build);
+    t.run_with_config(cfg).unwrap().assert_all_ok();
+
+    // the runner belongs to the build configuration, so the host build runs the entry directly
+    let t = batch_run::Batch::new();
+    t.run_fail("tests/runner/wrapped.rs")
+        .args(vec!["--entry-arg"]);
+    t.run().unwrap().assert_all_ok();
+}
+
+#[test]
+#[cfg(all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"))]
+fn target_runner_env() {
+    if env::var_os("BATCH_RUN_ENV_TEST").is_none() {
+        run_with_env(
+            "target_runner_env",
+            &[(
+                "CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUNNER",
+                "env BATCH_RUN_WRAPPED=1",
+            )],
+        );
+        return;
+    }
+
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/runner/wrapped.rs")
+        .args(vec!["--entry-arg"]);
+    let build = BuildConfig::new().with_target("x86_64-unknown-linux-gnu");
+    let cfg = Config::default().with_build(build);
+    t.run_with_config(cfg).unwrap().assert_all_ok();
+}
+
+/// Build the entry with either probe and check that it was linked through the wrapper,
+/// which is run in a child process with `BATCH_RUN_LINKER_LOG` set.
+fn assert_linked_through_wrapper(test: &str, build: BuildConfig, vars: &[(&str, &str)]) {
+    let log = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.log", test));
+    if env::var_os("BATCH_RUN_ENV_TEST").is_none() {
+        let _ = std::fs::remove_file(&log);
+        let mut vars = vars.to_vec();
+        vars.push(("BATCH_RUN_LINKER_LOG", log.to_str().unwrap()));
+        run_with_env(test, &vars);
+        return;
+    }
+
+    for probe in [Probe::Metadata, Probe::SourceBin] {
+        let _ = std::fs::remove_file(&log);
+        let t = batch_run::Batch::new();
+        t.run_pass("tests/probe/uses-deps.rs");
+        let cfg = Config::default().with_build(build.clone().with_probe(probe));
+        t.run_with_config(cfg).unwrap().assert_all_ok();
+        let linked = std::fs::read_to_string(&log).unwrap();
+        assert!(linked.contains("target/batch/"), "{:?}: {}", probe, linked);
+    }
+}
+
+#[test]
+#[cfg(all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"))]
+fn target_linker() {
+    let wrapper = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/linker/wrapper.sh");
+    let build = BuildConfig::new()
+        .with_target("x86_64-unknown-linux-gnu")
+        .with_linker(wrapper.to_str().unwrap());
+    assert_linked_through_wrapper("target_linker", build, &[]);
+}
+
+#[test]
+#[cfg(all(target_arch = "x86_64", target_os = "linux", target_env = "gnu"))]
+fn target_linker_env() {
+    let wrapper = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/linker/wrapper.sh");
+    // the separate target directory keeps the main one from being rebuilt with another linker
+    let target = Path::new(env!("CARGO_TARGET_TMPDIR")).join("target-linker");
+    assert_linked_through_wrapper(
+        "target_linker_env",
+        BuildConfig::new().with_target("x86_64-unknown-linux-gnu"),
+        &[
+            (
+                "CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_LINKER",
+                wrapper.to_str().unwrap(),
+            ),
+            ("CARGO_TARGET_DIR", target.to_str().unwrap()),
+        ],
+    );
+}
+
+#[test]
+fn jobs() {
+    let t = batch_run::Batch::new();
+    t.run_pass("tests/jobs/*.rs");
+    let reporter = RecordingReporter::default();
+    let cfg = Config::default()
+        .with_jobs(4)
+        .with_buffer()
+        .with_reporter(reporter.clone());
+    let res = t.run_with_config(cfg).unwrap();
+    res.assert_all_ok();
+
+    let names: Vec<_> = res
+        .outputs()
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "tests/jobs/1-slow.rs",
+            "tests/jobs/2-medium.rs",
+            "tests/jobs/3-fast.rs"
+        ]
+    );
+    // the entries run at the same time, so the faster ones finish first
+    let finished: Vec<_> = reporter
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|event| event.starts_with("finished"))
+        .cloned()
+        .collect();
+    assert_eq!(
+        finished,
+        [
+            "finished tests/jobs/3-fast.rs ok",
+            "finished tests/jobs/2-medium.rs ok",
+            "finished tests/jobs/1-slow.rs ok"
+        ]
+    );
+    // the summary reports the wall-clock time, not the sum of the entry durations
+    let total: Duration = res.outputs().iter().map(|(_, out)| out.duration()).sum();
+    let summary = res.summary();
+    assert_eq!(summary.duration(), res.duration());
+    assert!(summary.duration() < total);
+    assert!(summary.slowest().unwrap().1 <= summary.duration());
+}
+
+/// Run the single test in a child process with the given environment variables,
+/// so that they don't affect the batches of the other tests.
+fn run_with_env(test: &str, vars: &[(&str, &str)]) {
+    let status = Command::new(env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture"])
+        .env("BATCH_RUN_ENV_TEST", "1")
+        .envs(vars.iter().copied())
+        .status()
+        .unwrap();
+    assert!(status.success(), "{} failed with {:?}", test, vars);
+}
+
+#[test]
+fn jobs_env() {
+    if env::var_os("BATCH_RUN_ENV_TEST").is_none() {
+        run_with_env("jobs_env", &[("BATCH_RUN_JOBS", "3")]);
+        run_with_env("jobs_env", &[("BATCH_RUN_JOBS", "0")]);
+        run_with_env("jobs_env", &[("BATCH_RUN_JOBS", "many")]);
+        return;
+    }
+    let var = env::var("BATCH_RUN_JOBS").unwrap();
+    match Config::from_env() {
+        Ok(cfg) => assert_eq!((var.as_str(), cfg.jobs()), ("3", 3)),
+        Err(BatchError::ConfigError(ConfigError::JobsEnvVar(value))) => assert_eq!(value, *var),
+        Err(err) => panic!("Unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn parallel_batches() {
+    let list = Path::new(env!("CARGO_TARGET_TMPDIR")).join("parallel-batches.txt");
+    let _ = std::fs::remove_file(&list);
+
+    let batches: Vec<_> = (0..2)
+        .map(|_| {
+            let list = list.clone();
+            thread::spawn(move || {
+                let t = batch_run::Batch::new();
+                for _ in 0..2 {
+                    t.run_pass("tests/artifact/record-path.rs")
+                        .env("BATCH_RUN_ARTIFACTS", list.to_str().unwrap());
+                }
+                t.run_with_config(Config::default().with_buffer())
+                    .unwrap()
+                    .assert_all_ok();
+            })
+        })
+        .collect();
+    for batch in batches {
+        batch.join().unwrap();
+    }
+
+    // every entry was built into its own binary, which is removed afterwards
+    let artifacts = std::fs::read_to_string(&list).unwrap();
+    let mut artifacts: Vec<_> = artifacts.lines().collect();
+    artifacts.sort_unstable();
+    artifacts.dedup();
+    assert_eq!(artifacts.len(), 4);
+    for artifact in artifacts {
+        assert!(!Path::new(artifact).exists(), "{} is not removed", artifact);
+    }
+}
@@ -0,0 +1,6 @@
+use std::process::Command;
+
+fn main() {
+    // the grandchild inherits the output pipes and keeps them open after the entry exits
+    Command::new("sleep").arg("30").spawn().unwrap();
+}